@@ -0,0 +1,71 @@
+//! Allocations and time taken to query a large set again and again, as a UI
+//! redrawing every frame would, with and without reusing a `SetBuffer`.
+//!
+//! Run with `cargo bench --bench set_queries`.
+
+use recurrence::{Daily, End, RRule, Set, SetBuffer, Weekly};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant, SystemTime},
+};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const FRAMES: u32 = 1_000;
+const RULES: u64 = 200;
+
+fn main() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    let hour = Duration::from_secs(60 * 60);
+    let set = (0..RULES)
+        .map(|i| match i % 2 {
+            0 => RRule::Daily(Daily::utc(start + i as u32 * hour, 1, End::Never)),
+            _ => RRule::Weekly(Weekly::utc(start + i as u32 * hour, 1, End::Never)),
+        })
+        .fold(Set::new(), Set::rrule);
+
+    let fresh = measure(|frame| set.after(start + frame * hour).take(50).count());
+
+    let mut buffer = SetBuffer::new();
+    let reused = measure(|frame| {
+        set.after_with(start + frame * hour, &mut buffer)
+            .take(50)
+            .count()
+    });
+
+    println!("{} queries over a set of {} rules:", FRAMES, RULES);
+    println!("  after:      {:>8} allocations, {:?}", fresh.0, fresh.1);
+    println!("  after_with: {:>8} allocations, {:?}", reused.0, reused.1);
+}
+
+/// Allocations and time taken by `query` over every frame
+fn measure(mut query: impl FnMut(u32) -> usize) -> (usize, Duration) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let started = Instant::now();
+
+    for frame in 0..FRAMES {
+        assert_eq!(50, query(frame));
+    }
+
+    (
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        started.elapsed(),
+    )
+}
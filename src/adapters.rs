@@ -0,0 +1,293 @@
+//! Adapters for iterators of occurrences, which are always in ascending
+//! order, so they can be cut at an instant or paired up without rebuilding
+//! the rule.
+
+use std::time::{Duration, SystemTime};
+
+/// Cuts an ascending series of occurrences at an instant
+///
+/// ```
+/// use recurrence::{DatesExt as _, Daily, End};
+/// use std::time::{Duration, SystemTime};
+///
+/// let day = Duration::from_secs(24 * 60 * 60);
+/// let daily = Daily::utc(SystemTime::UNIX_EPOCH, 1, End::Never);
+///
+/// let week = daily.all().take_until(SystemTime::UNIX_EPOCH + 7 * day);
+/// assert_eq!(5, week.skip_until(SystemTime::UNIX_EPOCH + 2 * day).count());
+/// ```
+pub trait DatesExt: Iterator<Item = SystemTime> + Sized {
+    /// The occurrences before `t`
+    fn take_until(self, t: SystemTime) -> TakeUntil<Self> {
+        TakeUntil {
+            dates: self,
+            until: t,
+            done: false,
+        }
+    }
+
+    /// The occurrences at or after `t`, like `after` on the rule but for an
+    /// iterator already underway
+    fn skip_until(self, t: SystemTime) -> SkipUntil<Self> {
+        SkipUntil {
+            dates: self,
+            min: t,
+            done: false,
+        }
+    }
+
+    /// Each occurrence along with the one after it, e.g. to see how far
+    /// apart DST moved them
+    fn pairs(self) -> Pairs<Self> {
+        Pairs {
+            dates: self,
+            previous: None,
+        }
+    }
+
+    /// How long after each occurrence the next one comes, e.g. 23 or 25
+    /// hours for a daily rule across a DST change
+    fn gaps(self) -> Gaps<Self> {
+        Gaps {
+            pairs: self.pairs(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = SystemTime>> DatesExt for I {}
+
+/// See [`DatesExt::take_until`]
+#[derive(Clone, Debug)]
+pub struct TakeUntil<I> {
+    dates: I,
+    until: SystemTime,
+    /// set once an occurrence reached `until`, past which none are left
+    done: bool,
+}
+
+impl<I: Iterator<Item = SystemTime>> Iterator for TakeUntil<I> {
+    type Item = SystemTime;
+
+    fn next(&mut self) -> Option<SystemTime> {
+        if self.done {
+            return None;
+        }
+
+        match self.dates.next() {
+            Some(date) if date < self.until => Some(date),
+            _ => {
+                self.done = true;
+                None
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.done {
+            true => (0, Some(0)),
+            false => (0, self.dates.size_hint().1),
+        }
+    }
+}
+
+impl<I: DoubleEndedIterator<Item = SystemTime>> DoubleEndedIterator for TakeUntil<I> {
+    fn next_back(&mut self) -> Option<SystemTime> {
+        if self.done {
+            return None;
+        }
+
+        let until = self.until;
+        self.dates.by_ref().rev().find(|&date| date < until)
+    }
+}
+
+/// See [`DatesExt::skip_until`]
+#[derive(Clone, Debug)]
+pub struct SkipUntil<I> {
+    dates: I,
+    min: SystemTime,
+    /// set once an occurrence reached `min`, past which none are dropped
+    done: bool,
+}
+
+impl<I: Iterator<Item = SystemTime>> Iterator for SkipUntil<I> {
+    type Item = SystemTime;
+
+    fn next(&mut self) -> Option<SystemTime> {
+        if self.done {
+            return self.dates.next();
+        }
+
+        let min = self.min;
+        let date = self.dates.find(|&date| date >= min);
+        self.done = true;
+        date
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.done {
+            true => self.dates.size_hint(),
+            false => (0, self.dates.size_hint().1),
+        }
+    }
+}
+
+impl<I: DoubleEndedIterator<Item = SystemTime>> DoubleEndedIterator for SkipUntil<I> {
+    fn next_back(&mut self) -> Option<SystemTime> {
+        match self.dates.next_back() {
+            Some(date) if self.done || date >= self.min => Some(date),
+            // every occurrence left is before this one
+            _ => {
+                self.dates.by_ref().for_each(drop);
+                None
+            }
+        }
+    }
+}
+
+/// See [`DatesExt::pairs`]
+#[derive(Clone, Debug)]
+pub struct Pairs<I> {
+    dates: I,
+    previous: Option<SystemTime>,
+}
+
+impl<I: Iterator<Item = SystemTime>> Iterator for Pairs<I> {
+    type Item = (SystemTime, SystemTime);
+
+    fn next(&mut self) -> Option<(SystemTime, SystemTime)> {
+        let previous = match self.previous {
+            Some(previous) => previous,
+            None => self.dates.next()?,
+        };
+        let next = self.dates.next()?;
+        self.previous = Some(next);
+
+        Some((previous, next))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.dates.size_hint();
+        let pending = usize::from(self.previous.is_some());
+        let pairs = |dates: usize| dates.saturating_add(pending).saturating_sub(1);
+
+        (pairs(lower), upper.map(pairs))
+    }
+}
+
+/// See [`DatesExt::gaps`]
+#[derive(Clone, Debug)]
+pub struct Gaps<I> {
+    pairs: Pairs<I>,
+}
+
+impl<I: Iterator<Item = SystemTime>> Iterator for Gaps<I> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        let (previous, next) = self.pairs.next()?;
+        // occurrences never go backwards, at worst a set repeats one
+        Some(next.duration_since(previous).unwrap_or_default())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.pairs.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{daily, test_helpers::*, Daily, End};
+    use chrono::TimeZone as _;
+
+    #[test]
+    fn take_until() {
+        let dtstart = july_first();
+        let daily = Daily::utc(dtstart, 1, End::Count(10));
+        let until = dtstart + 3 * ONE_DAY;
+
+        let expected = vec![dtstart, dtstart + ONE_DAY, dtstart + 2 * ONE_DAY];
+        assert_eq!(expected, daily.all().take_until(until).collect::<Vec<_>>());
+        assert!(daily
+            .all_bounded()
+            .unwrap()
+            .take_until(until)
+            .rev()
+            .eq(expected.into_iter().rev()));
+        assert_eq!(0, daily.all().take_until(dtstart).count());
+    }
+
+    #[test]
+    fn skip_until() {
+        let dtstart = july_first();
+        let daily = Daily::utc(dtstart, 1, End::Count(10));
+        let min = dtstart + 6 * ONE_DAY + ONE_MINUTE;
+
+        let expected: Vec<_> = daily.after(min).collect();
+        assert_eq!(3, expected.len());
+        assert_eq!(expected, daily.all().skip_until(min).collect::<Vec<_>>());
+        assert!(daily
+            .all_bounded()
+            .unwrap()
+            .skip_until(min)
+            .rev()
+            .eq(expected.into_iter().rev()));
+
+        // an iterator already underway
+        let mut all = daily.all();
+        all.next();
+        assert!(all.skip_until(dtstart).eq(daily.all().skip(1)));
+    }
+
+    #[test]
+    fn pairs() {
+        // clocks fall back an hour on 2019-11-03
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 10, 30, 9, 0, 0)
+            .unwrap();
+        let daily = Daily::new(daily::Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            end: End::Count(7),
+            ..daily::Options::default()
+        });
+
+        let gaps: Vec<_> = daily
+            .all()
+            .pairs()
+            .map(|(previous, next)| next.duration_since(previous).unwrap())
+            .collect();
+        let mut expected = vec![ONE_DAY; 6];
+        expected[3] = ONE_DAY + ONE_HOUR;
+        assert_eq!(expected, gaps);
+
+        assert_eq!(6, daily.all().pairs().size_hint().0);
+        let never = Daily::utc(july_first(), 1, End::Never);
+        assert_eq!(usize::MAX - 1, never.all().pairs().size_hint().0);
+        assert_eq!(
+            0,
+            Daily::utc(july_first(), 1, End::Count(1))
+                .all()
+                .pairs()
+                .count()
+        );
+    }
+
+    #[test]
+    fn gaps() {
+        // clocks spring forward an hour on 2019-03-10
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 3, 8, 9, 0, 0)
+            .unwrap();
+        let daily = Daily::new(daily::Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            end: End::Count(5),
+            ..daily::Options::default()
+        });
+
+        let gaps: Vec<_> = daily.all().gaps().collect();
+        assert_eq!(vec![ONE_DAY, ONE_DAY - ONE_HOUR, ONE_DAY, ONE_DAY], gaps);
+    }
+}
@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::{fmt::Write as _, time::SystemTime};
+
+/// Renders up to `max` dates as `index,iso8601_local,unix_seconds` rows,
+/// preceded by a header row. Local times are rendered in `tz`.
+pub fn to_csv(dates: impl Iterator<Item = SystemTime>, tz: Tz, max: usize) -> String {
+    let mut csv = String::from("index,iso8601_local,unix_seconds\n");
+
+    for (index, date) in dates.take(max).enumerate() {
+        let date = DateTime::<Utc>::from(date).with_timezone(&tz);
+        writeln!(csv, "{},{},{}", index, date.to_rfc3339(), date.timestamp())
+            .expect("bug: writing to a String");
+    }
+
+    csv
+}
@@ -0,0 +1,415 @@
+use crate::{
+    dst::{self, Resolution},
+    tz_date_iterator::{
+        from_naive_to_system, from_system_to_naive, local_tz, BoundedIterator, TzDateIterator,
+    },
+    AmbiguousPolicy, End, NonexistentPolicy, RecurrenceError, RecurrenceIter, TimeZoneSpec,
+};
+use chrono::{DateTime, NaiveDateTime, NaiveTime, TimeZone as _};
+use chrono_tz::Tz;
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime},
+};
+
+/// Recurs every `interval`, keeping the wall-clock spacing between
+/// occurrences when the timezone offset changes (i.e. DST).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Custom {
+    interval: chrono::Duration,
+    timezone: TimeZoneSpec,
+    dtstart: NaiveDateTime,
+    end: End,
+    dst_ambiguous: AmbiguousPolicy,
+    dst_nonexistent: NonexistentPolicy,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Options {
+    pub interval: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "crate::rfc3339::option"))]
+    pub dtstart: Option<SystemTime>,
+    pub timezone: Option<TimeZoneSpec>,
+    pub end: End,
+    pub dst_ambiguous: AmbiguousPolicy,
+    pub dst_nonexistent: NonexistentPolicy,
+}
+
+impl Custom {
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Custom::try_new`]
+    pub fn new(options: Options) -> Self {
+        Self::try_new(options).expect("invalid custom options")
+    }
+
+    /// A recurrence computed in UTC, without looking up the local timezone
+    ///
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Custom::try_new`]
+    pub fn utc(dtstart: SystemTime, interval: Duration, end: End) -> Self {
+        Self::new(Options {
+            dtstart: Some(dtstart),
+            interval,
+            timezone: Some(chrono_tz::UTC.into()),
+            end,
+            ..Options::default()
+        })
+    }
+
+    pub fn try_new(options: Options) -> Result<Self, RecurrenceError> {
+        let dtstart = options.dtstart.unwrap_or_else(SystemTime::now);
+
+        if options.interval == Duration::from_secs(0) {
+            return Err(RecurrenceError::ZeroInterval);
+        }
+        let end = options.end.resolve(dtstart)?;
+        let interval = chrono::Duration::from_std(options.interval)
+            .map_err(|_| RecurrenceError::IntervalTooLarge)?;
+
+        Ok(Custom {
+            dtstart: from_system_to_naive(dtstart)?,
+            timezone: options
+                .timezone
+                .or_else(local_tz)
+                .ok_or(RecurrenceError::MissingTimezone)?,
+            interval,
+            end,
+            dst_ambiguous: options.dst_ambiguous,
+            dst_nonexistent: options.dst_nonexistent,
+        })
+    }
+
+    /// A rule stepping by `interval` in the place of another frequency
+    pub(crate) fn from_parts(
+        interval: chrono::Duration,
+        timezone: TimeZoneSpec,
+        dtstart: NaiveDateTime,
+        end: End,
+        dst_ambiguous: AmbiguousPolicy,
+        dst_nonexistent: NonexistentPolicy,
+    ) -> Self {
+        Custom {
+            interval,
+            timezone,
+            dtstart,
+            end,
+            dst_ambiguous,
+            dst_nonexistent,
+        }
+    }
+
+    /// The options that build this same rule back
+    pub fn to_options(&self) -> Options {
+        Options {
+            interval: self.interval.to_std().expect("bug: interval is positive"),
+            dtstart: Some(from_naive_to_system(self.dtstart)),
+            timezone: Some(self.timezone),
+            end: self.end,
+            dst_ambiguous: self.dst_ambiguous,
+            dst_nonexistent: self.dst_nonexistent,
+        }
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = SystemTime> {
+        self.all_local().map(SystemTime::from)
+    }
+
+    /// Like [`Custom::all`] but also walkable from the back, only for rules
+    /// whose end is bounded
+    pub fn all_bounded(&self) -> Option<BoundedIterator> {
+        self.dates().bounded()
+    }
+
+    /// Like [`Custom::all`] but keeping each occurrence in the rule's timezone,
+    /// ready to be formatted as a wall-clock time
+    pub fn all_local(&self) -> impl Iterator<Item = DateTime<TimeZoneSpec>> {
+        self.dates()
+    }
+
+    /// Every occurrence from dtstart on
+    pub(crate) fn dates(&self) -> TzDateIterator {
+        TzDateIterator::new(
+            self.timezone.from_utc_datetime(&self.dtstart),
+            self.interval,
+            self.end,
+        )
+        .ambiguous(self.dst_ambiguous)
+        .nonexistent(self.dst_nonexistent)
+    }
+
+    /// The occurrences whose wall-clock time in the rule's timezone is in
+    /// `[start, end)`, e.g. only business hours. A window whose end comes
+    /// before its start wraps past midnight, e.g. 22:00 to 06:00.
+    pub fn filter_time_range(
+        &self,
+        start: NaiveTime,
+        end: NaiveTime,
+    ) -> impl Iterator<Item = SystemTime> {
+        self.dates()
+            .filter(move |date| {
+                let time = date.time();
+                if start <= end {
+                    start <= time && time < end
+                } else {
+                    start <= time || time < end
+                }
+            })
+            .map(SystemTime::from)
+    }
+
+    /// Exports up to `max` occurrences as CSV rows of
+    /// `index,iso8601_local,unix_seconds`, rendering local times in `tz`
+    pub fn all_csv(&self, tz: Tz, max: usize) -> String {
+        crate::csv::to_csv(self.all(), tz, max)
+    }
+
+    pub fn after(&self, min: SystemTime) -> impl Iterator<Item = SystemTime> {
+        self.resume(min).1.map(SystemTime::from)
+    }
+
+    /// Each occurrence along with its index in the series
+    pub fn enumerate(&self) -> impl Iterator<Item = (usize, SystemTime)> {
+        self.all().enumerate()
+    }
+
+    /// Like [`Custom::after`] but each occurrence comes with its index in the
+    /// whole series, counting from dtstart rather than from `min`
+    pub fn enumerate_after(&self, min: SystemTime) -> impl Iterator<Item = (usize, SystemTime)> {
+        let (skipped, dates) = self.resume(min);
+        dates
+            .enumerate()
+            .map(move |(i, date)| (skipped + i, date.into()))
+    }
+
+    /// The occurrences at or after `min`, along with how many were skipped
+    pub(crate) fn resume(&self, min: SystemTime) -> (usize, TzDateIterator) {
+        let dtstart = self.timezone.from_utc_datetime(&self.dtstart);
+
+        TzDateIterator::new(dtstart, self.interval, self.end)
+            .ambiguous(self.dst_ambiguous)
+            .nonexistent(self.dst_nonexistent)
+            .resume(min, |min| {
+                // min's wall-clock time can be before dtstart's when both are
+                // in the hour repeated as clocks fall back
+                let elapsed = nanos(min.naive_local() - dtstart.naive_local());
+                let interval = nanos(self.interval);
+                let mut steps = (elapsed + interval - 1).div_euclid(interval).max(0);
+
+                let mut cursor = dtstart
+                    .naive_local()
+                    .checked_add_signed(from_nanos(steps * interval))?;
+                // the wall-clock estimate can fall short right around an offset change
+                while matches!(
+                    dst::resolve(
+                        self.timezone,
+                        cursor,
+                        self.dst_ambiguous,
+                        self.dst_nonexistent,
+                    ),
+                    Resolution::At(c) if c < min
+                ) {
+                    cursor = cursor.checked_add_signed(self.interval)?;
+                    steps += 1;
+                }
+
+                Some((usize::try_from(steps).ok()?, cursor))
+            })
+    }
+
+    /// The first occurrence at or after `min`
+    pub fn first_after(&self, min: SystemTime) -> Option<SystemTime> {
+        self.after(min).next()
+    }
+}
+
+fn nanos(duration: chrono::Duration) -> i128 {
+    duration.num_seconds() as i128 * 1_000_000_000 + duration.subsec_nanos() as i128
+}
+
+fn from_nanos(nanos: i128) -> chrono::Duration {
+    chrono::Duration::seconds((nanos / 1_000_000_000) as i64)
+        + chrono::Duration::nanoseconds((nanos % 1_000_000_000) as i64)
+}
+
+/// Iterates over [`Custom::all`]
+impl IntoIterator for &Custom {
+    type Item = SystemTime;
+    type IntoIter = RecurrenceIter;
+
+    fn into_iter(self) -> RecurrenceIter {
+        self.dates().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use chrono::Timelike as _;
+
+    #[test]
+    fn dtstart() {
+        let dtstart = july_first();
+
+        let dates = super::Custom::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            interval: ONE_HOUR,
+            ..Options::default()
+        });
+
+        assert_eq!(dtstart, dates.all().next().unwrap());
+    }
+
+    #[test]
+    fn interval() {
+        let dtstart = july_first();
+        let dates = super::Custom::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            interval: 90 * ONE_MINUTE,
+            ..Options::default()
+        });
+        let mut dates = dates.all().skip(1);
+
+        assert_eq!(dtstart + 90 * ONE_MINUTE, dates.next().unwrap());
+        assert_eq!(dtstart + 180 * ONE_MINUTE, dates.next().unwrap());
+    }
+
+    #[test]
+    fn after_across_fall_back() {
+        // clocks fall back from 02:00 EDT to 01:00 EST on 2019-11-03
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 11, 3, 1, 45, 0)
+            .earliest()
+            .unwrap();
+        let dates = super::Custom::new(Options {
+            dtstart: Some(dtstart.into()),
+            interval: 20 * ONE_MINUTE,
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            end: End::Count(20),
+            ..Options::default()
+        });
+
+        // 01:05 EST, whose wall-clock time is before dtstart's
+        let min = SystemTime::from(dtstart) + 20 * ONE_MINUTE;
+        // 02:05 EST, as 01:45 EDT + 20 minutes on the wall clock
+        assert_eq!(
+            Some(SystemTime::from(dtstart) + 80 * ONE_MINUTE),
+            dates.first_after(min)
+        );
+
+        for minutes in 0..6 * 60 {
+            let min = SystemTime::from(dtstart) - ONE_HOUR + minutes * ONE_MINUTE;
+            let walked: Vec<_> = dates.all().filter(|&date| date >= min).collect();
+            assert_eq!(walked, dates.after(min).collect::<Vec<_>>(), "{:?}", min);
+            assert_eq!(
+                dates.all().position(|date| date >= min),
+                dates.enumerate_after(min).next().map(|(i, _)| i)
+            );
+        }
+    }
+
+    #[test]
+    fn zero_interval() {
+        let dates = super::Custom::try_new(Options::default());
+
+        assert_eq!(Some(RecurrenceError::ZeroInterval), dates.err());
+    }
+
+    #[test]
+    fn dst_changes() {
+        let midnight = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 3, 10, 0, 0, 0)
+            .unwrap();
+        let next_midnight = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 3, 11, 0, 0, 0)
+            .unwrap();
+
+        let dates = super::Custom::new(Options {
+            dtstart: Some(midnight.into()),
+            interval: 90 * ONE_MINUTE,
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            end: End::Until(SystemTime::from(next_midnight) - ONE_MINUTE),
+            ..Options::default()
+        });
+        let dates: Vec<_> = dates.all().collect();
+
+        assert_eq!(16, dates.len());
+        // clocks jump from 02:00 to 03:00 so 01:30 -> 03:00 is only half an hour
+        assert_eq!(SystemTime::from(midnight) + 90 * ONE_MINUTE, dates[1]);
+        assert_eq!(dates[1] + 30 * ONE_MINUTE, dates[2]);
+        assert_eq!(SystemTime::from(next_midnight) - 90 * ONE_MINUTE, dates[15]);
+    }
+
+    #[test]
+    fn filter_time_range() {
+        let midnight = chrono_tz::Europe::Paris
+            .with_ymd_and_hms(2021, 3, 1, 0, 0, 0)
+            .unwrap();
+        let hourly = super::Custom::new(Options {
+            dtstart: Some(midnight.into()),
+            interval: ONE_HOUR,
+            timezone: Some(chrono_tz::Europe::Paris.into()),
+            end: End::Count(48),
+            ..Options::default()
+        });
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let hours = |dates: Vec<SystemTime>| -> Vec<u32> {
+            dates
+                .into_iter()
+                .map(DateTime::<chrono::Utc>::from)
+                .map(|date| date.with_timezone(&chrono_tz::Europe::Paris).hour())
+                .collect()
+        };
+
+        let business = hours(hourly.filter_time_range(at(9, 0), at(17, 0)).collect());
+        let mut expected: Vec<u32> = (9..17).collect();
+        expected.extend(9..17);
+        assert_eq!(expected, business);
+
+        let night = hours(hourly.filter_time_range(at(22, 0), at(2, 0)).collect());
+        assert_eq!(vec![0, 1, 22, 23, 0, 1, 22, 23], night);
+    }
+
+    #[test]
+    fn nanosecond_precision() {
+        let dtstart = SystemTime::UNIX_EPOCH - ONE_DAY + Duration::from_nanos(123_456_789);
+        let dates = super::Custom::utc(dtstart, 90 * ONE_MINUTE, End::Count(1000));
+        let nanos = |date: SystemTime| DateTime::<chrono::Utc>::from(date).nanosecond();
+
+        assert!(dates.all().all(|date| nanos(date) == 123_456_789));
+        assert_eq!(
+            Some(dtstart + 999 * 90 * ONE_MINUTE),
+            dates.all_bounded().unwrap().next_back()
+        );
+        assert_eq!(
+            Some(dtstart + 90 * ONE_MINUTE),
+            dates.after(dtstart + Duration::from_nanos(1)).next()
+        );
+    }
+
+    #[test]
+    fn after_between_occurrences() {
+        let dtstart = july_first();
+
+        let dates = super::Custom::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            interval: 90 * ONE_MINUTE,
+            end: End::Count(5),
+            ..Options::default()
+        });
+        let mut after = dates.after(dtstart + 2 * ONE_HOUR);
+
+        assert_eq!(Some(dtstart + 180 * ONE_MINUTE), after.next());
+        assert_eq!(2, after.count());
+    }
+}
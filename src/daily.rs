@@ -1,185 +1,2155 @@
-use crate::{tz_date_iterator::TzDateIterator, End};
-use chrono::{NaiveDateTime, TimeZone as _};
+use crate::{
+    describe,
+    dst::{self, Resolution},
+    tz_date_iterator::{
+        from_naive_to_system, from_system_to_naive, from_timestamp, local_tz, BoundedIterator,
+        CountedIterator, DaysOff, Step, TzDateIterator,
+    },
+    AmbiguousPolicy, Custom, End, NonexistentPolicy, RecurrenceError, RecurrenceIter, TimeZoneSpec,
+};
+use chrono::{
+    DateTime, Datelike as _, NaiveDate, NaiveDateTime, NaiveTime, TimeZone as _, Timelike as _,
+    Weekday,
+};
 use chrono_tz::Tz;
-use std::time::SystemTime;
+use std::{
+    collections::HashSet,
+    convert::TryFrom,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Daily {
-    interval: u32,
-    timezone: Tz,
+    interval: u64,
+    timezone: TimeZoneSpec,
     dtstart: NaiveDateTime,
     end: End,
+    dst_ambiguous: AmbiguousPolicy,
+    dst_nonexistent: NonexistentPolicy,
+    duration: Duration,
+    interval_duration: Option<chrono::Duration>,
+    days_off: Arc<DaysOff>,
+    by_hour: Vec<u32>,
+    by_minute: Vec<u32>,
+    by_second: Vec<u32>,
+    /// the times of the day `by_hour`, `by_minute` and `by_second` expand
+    /// each day into, empty when they don't
+    times: Arc<[NaiveTime]>,
+    all_day: bool,
 }
 
-#[derive(Default)]
+#[derive(Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Options {
-    pub interval: Option<u32>,
+    pub interval: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::rfc3339::option"))]
     pub dtstart: Option<SystemTime>,
-    pub timezone: Option<Tz>,
+    pub timezone: Option<TimeZoneSpec>,
+    /// The timezone by name, e.g. `Europe/Paris` or `+05:30`, parsed when
+    /// the rule is built. Takes precedence over `timezone`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub timezone_name: Option<String>,
     pub end: End,
+    pub dst_ambiguous: AmbiguousPolicy,
+    pub dst_nonexistent: NonexistentPolicy,
+    /// Date-only occurrences: each one lands on the local midnight starting
+    /// its day, whatever the UTC offset is then. The DST policies don't
+    /// apply: a midnight DST skips or repeats moves to the day's first
+    /// instant.
+    pub all_day: bool,
+    /// How long each occurrence lasts, zero when unset
+    pub duration: Option<Duration>,
+    /// Steps by this wall-clock duration, e.g. 36 hours, instead of by
+    /// whole days. Takes precedence over `interval`.
+    pub interval_duration: Option<Duration>,
+    /// Business days only: occurrences never land on a Saturday or Sunday
+    /// in the rule's timezone, and the interval counts business days. A
+    /// dtstart on a weekend moves to the Monday after it. Has no effect
+    /// with `interval_duration`.
+    pub skip_weekends: bool,
+    /// Dates in the rule's timezone left out just like weekends are with
+    /// `skip_weekends`, e.g. for a schedule of working days
+    pub holidays: HashSet<NaiveDate>,
+    /// Lands on each of these hours of the day, in ascending order, instead
+    /// of only dtstart's, keeping dtstart's minutes and seconds unless
+    /// `by_minute` or `by_second` list some. An hour skipped when clocks
+    /// spring forward follows `dst_nonexistent`, so it can be shifted onto
+    /// the next one. A dtstart at another time moves to the next one
+    /// listed. Has no effect with `all_day` or `interval_duration`.
+    pub by_hour: Vec<u32>,
+    /// Like `by_hour` but for the minutes, at each of the hours. Every
+    /// combination of the listed hours, minutes and seconds is an
+    /// occurrence, in chronological order.
+    pub by_minute: Vec<u32>,
+    /// Like `by_hour` but for the seconds, at each of the minutes
+    pub by_second: Vec<u32>,
+}
+
+/// Builds a [`Daily`] one option at a time
+///
+/// ```
+/// use recurrence::{daily, Daily, End};
+/// use std::time::{Duration, SystemTime};
+///
+/// let dtstart = SystemTime::UNIX_EPOCH + Duration::from_secs(1_593_576_285);
+///
+/// let built = Daily::builder()
+///     .dtstart(dtstart)
+///     .interval(2)
+///     .timezone(chrono_tz::UTC)
+///     .count(3)
+///     .build()
+///     .unwrap();
+///
+/// let literal = Daily::new(daily::Options {
+///     dtstart: Some(dtstart),
+///     interval: Some(2),
+///     timezone: Some(chrono_tz::UTC.into()),
+///     end: End::Count(3),
+///     ..daily::Options::default()
+/// });
+///
+/// assert!(built.all().eq(literal.all()));
+/// ```
+#[derive(Debug, Default)]
+pub struct Builder {
+    options: Options,
+}
+
+impl Builder {
+    pub fn interval(mut self, interval: u64) -> Self {
+        self.options.interval = Some(interval);
+        self
+    }
+
+    pub fn timezone(mut self, timezone: impl Into<TimeZoneSpec>) -> Self {
+        self.options.timezone = Some(timezone.into());
+        self
+    }
+
+    /// Sets the timezone by name, see [`Options::timezone_name`]
+    pub fn timezone_name(mut self, name: impl Into<String>) -> Self {
+        self.options.timezone_name = Some(name.into());
+        self
+    }
+
+    pub fn dtstart(mut self, dtstart: SystemTime) -> Self {
+        self.options.dtstart = Some(dtstart);
+        self
+    }
+
+    /// Sets the dtstart from seconds since the Unix epoch, negative before it
+    pub fn dtstart_timestamp(self, secs: i64) -> Self {
+        self.dtstart(from_timestamp(secs))
+    }
+
+    /// Sets both the dtstart and the timezone from a chrono `DateTime`
+    pub fn zoned_dtstart<Z>(self, dtstart: DateTime<Z>) -> Self
+    where
+        Z: chrono::TimeZone + Into<TimeZoneSpec>,
+    {
+        self.timezone(dtstart.timezone()).dtstart(dtstart.into())
+    }
+
+    pub fn count(mut self, count: usize) -> Self {
+        self.options.end = self.options.end.with_count(count);
+        self
+    }
+
+    pub fn until(mut self, until: SystemTime) -> Self {
+        self.options.end = self.options.end.with_until(until);
+        self
+    }
+
+    pub fn dst_ambiguous(mut self, policy: AmbiguousPolicy) -> Self {
+        self.options.dst_ambiguous = policy;
+        self
+    }
+
+    pub fn dst_nonexistent(mut self, policy: NonexistentPolicy) -> Self {
+        self.options.dst_nonexistent = policy;
+        self
+    }
+
+    pub fn all_day(mut self) -> Self {
+        self.options.all_day = true;
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.options.duration = Some(duration);
+        self
+    }
+
+    pub fn skip_weekends(mut self) -> Self {
+        self.options.skip_weekends = true;
+        self
+    }
+
+    /// Leaves out `holidays`, see [`Options::holidays`]
+    pub fn holidays(mut self, holidays: impl IntoIterator<Item = NaiveDate>) -> Self {
+        self.options.holidays.extend(holidays);
+        self
+    }
+
+    /// Adds hours of the day to land on, see [`Options::by_hour`]
+    pub fn by_hour(mut self, hours: impl IntoIterator<Item = u32>) -> Self {
+        self.options.by_hour.extend(hours);
+        self
+    }
+
+    /// Adds minutes to land on, see [`Options::by_minute`]
+    pub fn by_minute(mut self, minutes: impl IntoIterator<Item = u32>) -> Self {
+        self.options.by_minute.extend(minutes);
+        self
+    }
+
+    /// Adds seconds to land on, see [`Options::by_second`]
+    pub fn by_second(mut self, seconds: impl IntoIterator<Item = u32>) -> Self {
+        self.options.by_second.extend(seconds);
+        self
+    }
+
+    /// Sets the step between occurrences, see [`Options::interval_duration`]
+    pub fn interval_duration(mut self, interval: Duration) -> Self {
+        self.options.interval_duration = Some(interval);
+        self
+    }
+
+    pub fn build(self) -> Result<Daily, RecurrenceError> {
+        Daily::try_new(self.options)
+    }
 }
 
 impl Daily {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Daily::try_new`]
     pub fn new(options: Options) -> Self {
-        Daily {
-            dtstart: from_system_to_naive(options.dtstart.unwrap_or_else(|| SystemTime::now())),
-            timezone: options.timezone.unwrap_or_else(local_tz),
-            interval: options.interval.unwrap_or(1),
-            end: options.end,
+        Self::try_new(options).expect("invalid daily options")
+    }
+
+    /// A recurrence computed in UTC, without looking up the local timezone
+    ///
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Daily::try_new`]
+    pub fn utc(dtstart: SystemTime, interval: u64, end: End) -> Self {
+        Self::new(Options {
+            dtstart: Some(dtstart),
+            interval: Some(interval),
+            timezone: Some(chrono_tz::UTC.into()),
+            end,
+            ..Options::default()
+        })
+    }
+
+    pub fn try_new(options: Options) -> Result<Self, RecurrenceError> {
+        let dtstart = options.dtstart.unwrap_or_else(SystemTime::now);
+        let interval = options.interval.unwrap_or(1);
+
+        if interval == 0 {
+            return Err(RecurrenceError::ZeroInterval);
+        }
+        // a step that long could not be taken even once
+        i64::try_from(interval)
+            .ok()
+            .and_then(chrono::Duration::try_days)
+            .ok_or(RecurrenceError::IntervalTooLarge)?;
+        let interval_duration = match options.interval_duration {
+            Some(interval) if interval.is_zero() => return Err(RecurrenceError::ZeroInterval),
+            Some(interval) => Some(
+                chrono::Duration::from_std(interval)
+                    .map_err(|_| RecurrenceError::IntervalTooLarge)?,
+            ),
+            None => None,
+        };
+        if let Some(&hour) = options.by_hour.iter().find(|&&hour| hour > 23) {
+            return Err(RecurrenceError::InvalidHour(hour));
+        }
+        if let Some(&minute) = options.by_minute.iter().find(|&&minute| minute > 59) {
+            return Err(RecurrenceError::InvalidMinute(minute));
+        }
+        if let Some(&second) = options.by_second.iter().find(|&&second| second > 59) {
+            return Err(RecurrenceError::InvalidSecond(second));
+        }
+        let end = options.end.resolve(dtstart)?;
+
+        let mut dtstart = from_system_to_naive(dtstart)?;
+        let timezone = match options.timezone_name {
+            Some(name) => Some(name.parse()?),
+            None => options.timezone,
+        };
+        let timezone = timezone
+            .or_else(local_tz)
+            .ok_or(RecurrenceError::MissingTimezone)?;
+        let days_off = Arc::new(DaysOff {
+            weekends: options.skip_weekends,
+            holidays: options.holidays,
+        });
+        if !days_off.is_empty() {
+            // past the last holiday and a weekend right after it, however
+            // the holidays before it are broken up by weekends
+            let start = timezone.from_utc_datetime(&dtstart).date_naive();
+            let to_last = days_off
+                .holidays
+                .iter()
+                .max()
+                .map_or(0, |&last| (last - start).num_days().max(0));
+            let within = to_last as u64 + 3;
+            dtstart = dst::first_day(timezone, dtstart, |date| !days_off.contains(date), within)?;
+        }
+        let sorted = |mut listed: Vec<u32>| {
+            listed.sort_unstable();
+            listed.dedup();
+            listed
+        };
+        let by_hour = sorted(options.by_hour);
+        let by_minute = sorted(options.by_minute);
+        let by_second = sorted(options.by_second);
+        let mut times = Vec::new();
+        let (dst_ambiguous, dst_nonexistent) = if options.all_day {
+            dst::ALL_DAY_POLICIES
+        } else {
+            (options.dst_ambiguous, options.dst_nonexistent)
+        };
+        if options.all_day {
+            dtstart = dst::all_day(timezone, dtstart);
+        } else if interval_duration.is_none()
+            && !(by_hour.is_empty() && by_minute.is_empty() && by_second.is_empty())
+        {
+            let local = timezone.from_utc_datetime(&dtstart).naive_local();
+            times = times_of_day(local.time(), &by_hour, &by_minute, &by_second);
+            if !times.contains(&local.time()) {
+                let days = days_step(interval, &days_off);
+                let next = Step::Times(Box::new(days), times.as_slice().into())
+                    .shift(local, 1)
+                    .ok_or(RecurrenceError::DateOutOfRange)?;
+                dtstart = dst::to_utc(timezone, next);
+            }
+        }
+
+        Ok(Daily {
+            dtstart,
+            timezone,
+            interval,
+            end,
+            dst_ambiguous,
+            dst_nonexistent,
+            duration: options.duration.unwrap_or_default(),
+            interval_duration,
+            days_off,
+            by_hour,
+            by_minute,
+            by_second,
+            times: times.into(),
+            all_day: options.all_day,
+        })
+    }
+
+    /// The options that build this same rule back
+    pub fn to_options(&self) -> Options {
+        Options {
+            interval: Some(self.interval),
+            dtstart: Some(from_naive_to_system(self.dtstart)),
+            timezone: Some(self.timezone),
+            timezone_name: None,
+            end: self.end,
+            dst_ambiguous: self.dst_ambiguous,
+            dst_nonexistent: self.dst_nonexistent,
+            all_day: self.all_day,
+            duration: Some(self.duration),
+            interval_duration: self
+                .interval_duration
+                .map(|interval| interval.to_std().expect("bug: interval is positive")),
+            skip_weekends: self.days_off.weekends,
+            holidays: self.days_off.holidays.clone(),
+            by_hour: self.by_hour.clone(),
+            by_minute: self.by_minute.clone(),
+            by_second: self.by_second.clone(),
+        }
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = SystemTime> {
+        self.all_local().map(SystemTime::from)
+    }
+
+    /// Like [`Daily::all`] but also walkable from the back, only for rules
+    /// whose end is bounded
+    pub fn all_bounded(&self) -> Option<BoundedIterator> {
+        self.dates().bounded()
+    }
+
+    /// Each occurrence as its `(start, end)` pair
+    ///
+    /// The duration is elapsed time, so an event that spans a DST change
+    /// ends at a wall-clock time an hour off from the one it would have on
+    /// other days, e.g. a one hour event at 01:30 ends at 03:30 when clocks
+    /// spring forward at 02:00.
+    pub fn all_with_duration(&self) -> impl Iterator<Item = (SystemTime, SystemTime)> {
+        let duration = self.duration;
+        self.all().map(move |start| (start, start + duration))
+    }
+
+    /// Like [`Daily::all`] but never longer than its count, only for rules
+    /// ending with `End::Count` and without an `Error` DST policy that could
+    /// stop them short
+    pub fn all_counted(&self) -> Option<CountedIterator> {
+        self.dates().counted()
+    }
+
+    /// Like [`Daily::all`] but keeping each occurrence in the rule's timezone,
+    /// ready to be formatted as a wall-clock time
+    pub fn all_local(&self) -> impl Iterator<Item = DateTime<TimeZoneSpec>> {
+        self.dates()
+    }
+
+    /// Like [`Daily::all_local`] but showing each occurrence in `display_tz`.
+    /// The occurrences are still computed in the rule's timezone, so they
+    /// follow its DST changes rather than those of `display_tz`.
+    pub fn all_in(&self, display_tz: Tz) -> impl Iterator<Item = DateTime<Tz>> {
+        self.dates()
+            .map(move |date| date.with_timezone(&display_tz))
+    }
+
+    /// Every occurrence moved by `by`, which can be negative, e.g. a reminder
+    /// 15 minutes before each one with `chrono::Duration::minutes(-15)`
+    pub fn shifted(&self, by: chrono::Duration) -> impl Iterator<Item = SystemTime> {
+        self.dates().map(move |date| (date + by).into())
+    }
+
+    /// The occurrences that fall on one of `days` in the rule's timezone,
+    /// e.g. only the weekends of a daily rule
+    pub fn filter_weekday(&self, days: &[Weekday]) -> impl Iterator<Item = SystemTime> {
+        let days = days.to_vec();
+        self.dates()
+            .filter(move |date| days.contains(&date.weekday()))
+            .map(SystemTime::from)
+    }
+
+    /// Every occurrence pushed later by a pseudo-random amount in `[0, max)`,
+    /// e.g. to spread the load of many schedules. The amount only depends on
+    /// `seed` and the occurrence's index in the series, so it is the same
+    /// every time, and never reaches the next occurrence so the order is kept.
+    pub fn jittered(&self, max: Duration, seed: u64) -> impl Iterator<Item = SystemTime> {
+        let mut dates = self.all().enumerate().peekable();
+
+        std::iter::from_fn(move || {
+            let (i, date) = dates.next()?;
+            let bound = match dates.peek() {
+                Some(&(_, next)) => max.min(next.duration_since(date).unwrap_or_default()),
+                None => max,
+            };
+            Some(date + scale(splitmix64(seed ^ splitmix64(i as u64)), bound))
+        })
+    }
+
+    /// Every occurrence from dtstart on
+    pub(crate) fn dates(&self) -> TzDateIterator {
+        if let Some(custom) = self.by_duration() {
+            return custom.dates();
+        }
+
+        let mut step = days_step(self.interval, &self.days_off);
+        if !self.times.is_empty() {
+            step = Step::Times(Box::new(step), self.times.clone());
+        }
+
+        TzDateIterator::new(
+            self.timezone.from_utc_datetime(&self.dtstart),
+            step,
+            self.end,
+        )
+        .ambiguous(self.dst_ambiguous)
+        .nonexistent(self.dst_nonexistent)
+    }
+
+    /// Exports up to `max` occurrences as CSV rows of
+    /// `index,iso8601_local,unix_seconds`, rendering local times in `tz`
+    pub fn all_csv(&self, tz: Tz, max: usize) -> String {
+        crate::csv::to_csv(self.all(), tz, max)
+    }
+
+    /// The occurrences at or after `min`: one landing exactly on `min` comes
+    /// first
+    pub fn after(&self, min: SystemTime) -> impl Iterator<Item = SystemTime> {
+        self.resume(min).1.map(SystemTime::from)
+    }
+
+    /// Each occurrence along with its index in the series
+    pub fn enumerate(&self) -> impl Iterator<Item = (usize, SystemTime)> {
+        self.all().enumerate()
+    }
+
+    /// Like [`Daily::after`] but each occurrence comes with its index in the
+    /// whole series, counting from dtstart rather than from `min`
+    pub fn enumerate_after(&self, min: SystemTime) -> impl Iterator<Item = (usize, SystemTime)> {
+        let (skipped, dates) = self.resume(min);
+        dates
+            .enumerate()
+            .map(move |(i, date)| (skipped + i, date.into()))
+    }
+
+    /// The occurrences at or after `min`, along with how many were skipped
+    pub(crate) fn resume(&self, min: SystemTime) -> (usize, TzDateIterator) {
+        if let Some(custom) = self.by_duration() {
+            return custom.resume(min);
+        }
+        if !self.days_off.is_empty() || !self.times.is_empty() {
+            // working days and times of the day can't be counted off the
+            // calendar
+            return self.dates().skip_before(min);
+        }
+
+        let dtstart = self.timezone.from_utc_datetime(&self.dtstart);
+
+        TzDateIterator::new(
+            dtstart,
+            chrono::Duration::days(self.interval as i64),
+            self.end,
+        )
+        .ambiguous(self.dst_ambiguous)
+        .nonexistent(self.dst_nonexistent)
+        .resume(min, |min| {
+            let time = dtstart.time();
+            let start_date = dtstart.date_naive();
+            let mut date = min.date_naive();
+
+            if time < min.time() {
+                date = date.succ_opt()?;
+            }
+
+            // round up to the next day that is a whole number of intervals
+            // from dtstart's
+            let days = (date - start_date).num_days() as u64;
+            let mut skipped = days.div_ceil(self.interval);
+            let local = |skipped: u64| {
+                let days = i64::try_from(skipped.checked_mul(self.interval)?).ok()?;
+                let date = start_date.checked_add_signed(chrono::Duration::try_days(days)?)?;
+                Some(date.and_time(time))
+            };
+
+            // an occurrence moved by an offset change can land on either
+            // side of min whatever its wall-clock time
+            let lands = |skipped| match dst::resolve(
+                self.timezone,
+                local(skipped)?,
+                self.dst_ambiguous,
+                self.dst_nonexistent,
+            ) {
+                Resolution::At(date) => Some(date),
+                _ => None,
+            };
+            if matches!(lands(skipped), Some(date) if date < min) {
+                skipped += 1;
+            } else if skipped > 0 && matches!(lands(skipped - 1), Some(date) if date >= min) {
+                skipped -= 1;
+            }
+
+            Some((usize::try_from(skipped).ok()?, local(skipped)?))
+        })
+    }
+
+    pub(crate) fn is_all_day(&self) -> bool {
+        self.all_day
+    }
+
+    /// Stepping by a duration rather than by days is what a custom rule does
+    fn by_duration(&self) -> Option<Custom> {
+        self.interval_duration.map(|interval| {
+            Custom::from_parts(
+                interval,
+                self.timezone,
+                self.dtstart,
+                self.end,
+                self.dst_ambiguous,
+                self.dst_nonexistent,
+            )
+        })
+    }
+
+    /// Up to the first `n` occurrences, fewer if the rule ends before then
+    pub fn first_n(&self, n: usize) -> Vec<SystemTime> {
+        self.all().take(n).collect()
+    }
+
+    /// Up to `n` occurrences at or after `min`, fewer if the rule ends
+    /// before then
+    pub fn after_n(&self, min: SystemTime, n: usize) -> Vec<SystemTime> {
+        self.after(min).take(n).collect()
+    }
+
+    /// Collects at most `max` occurrences whatever the end, safe even for
+    /// rules that never end. The flag is set when occurrences were left out.
+    pub fn collect_capped(&self, max: usize) -> (Vec<SystemTime>, bool) {
+        let mut dates = self.all();
+        let capped = dates.by_ref().take(max).collect();
+
+        (capped, dates.next().is_some())
+    }
+
+    /// The DST transitions that shift the occurrences in `[start, end)`
+    /// relative to each other, see [`Daily::all_local`]
+    pub fn dst_transitions(&self, start: SystemTime, end: SystemTime) -> Vec<SystemTime> {
+        let (_, dates) = self.resume(start);
+        dst::transitions(dates.take_while(|date| SystemTime::from(*date) < end))
+    }
+
+    /// What the rule does in words, e.g. "Every weekday, 10 times."
+    pub fn describe(&self) -> String {
+        let every = match self.interval_duration {
+            Some(interval) => {
+                describe::every_duration(interval.to_std().expect("bug: interval is positive"))
+            }
+            None if self.days_off.weekends => describe::every(self.interval, "weekday"),
+            None => describe::every(self.interval, "day"),
+        };
+        let except = if self.days_off.holidays.is_empty() {
+            ""
+        } else {
+            " except holidays"
+        };
+
+        format!(
+            "{}{}{}.",
+            every,
+            except,
+            describe::end(self.end, self.timezone)
+        )
+    }
+
+    /// The number of occurrences, `None` when the rule never ends. Bounded
+    /// rules are counted off the calendar without walking every occurrence,
+    /// see [`Daily::all`] for the exact series it matches.
+    pub fn estimated_count(&self) -> Option<usize> {
+        self.dates().estimated_count()
+    }
+
+    /// The occurrences within the calendar year `year` in the rule's timezone,
+    /// none for a year past the range of dates chrono can represent
+    pub fn in_year(&self, year: i32) -> impl Iterator<Item = SystemTime> {
+        let (start, end) = dst::year_bounds(self.timezone, year)
+            .unwrap_or((SystemTime::UNIX_EPOCH, SystemTime::UNIX_EPOCH));
+        self.after(start).take_while(move |&date| date < end)
+    }
+
+    /// The first occurrence at or after `min`
+    pub fn first_after(&self, min: SystemTime) -> Option<SystemTime> {
+        self.after(min).next()
+    }
+
+    /// Whether `t` is exactly one of the occurrences, down to the
+    /// nanosecond and after any DST correction
+    pub fn is_occurrence(&self, t: SystemTime) -> bool {
+        self.first_after(t) == Some(t)
+    }
+
+    /// The first occurrence strictly after `t`, counted off the calendar like
+    /// [`Daily::after`] rather than walked to
+    pub fn next_after(&self, t: SystemTime) -> Option<SystemTime> {
+        self.first_after(t.checked_add(Duration::from_nanos(1))?)
+    }
+
+    /// The last occurrence strictly before `t`, `None` when `t` is not after
+    /// dtstart. How many occurrences come before `t` is counted off the
+    /// calendar like [`Daily::after`], then jumped to like `nth`.
+    pub fn previous_before(&self, t: SystemTime) -> Option<SystemTime> {
+        if self.dst_nonexistent == NonexistentPolicy::Skip {
+            // the days left out of the gap still count as skipped
+            return self.all().take_while(|&date| date < t).last();
+        }
+
+        let (before, _) = self.resume(t);
+        // the series may end before `t`
+        let before = self
+            .estimated_count()
+            .map_or(before, |count| count.min(before));
+        self.dates()
+            .nth(before.checked_sub(1)?)
+            .map(SystemTime::from)
+    }
+}
+
+/// Steps of `interval` days, or working days when some are off
+fn days_step(interval: u64, days_off: &Arc<DaysOff>) -> Step {
+    if days_off.is_empty() {
+        chrono::Duration::days(interval as i64).into()
+    } else {
+        Step::WorkingDays(interval, days_off.clone())
+    }
+}
+
+/// Every combination of the listed hours, minutes and seconds, taking the
+/// ones left unlisted from `local`. Sorted lists make for sorted times.
+fn times_of_day(
+    local: NaiveTime,
+    hours: &[u32],
+    minutes: &[u32],
+    seconds: &[u32],
+) -> Vec<NaiveTime> {
+    let or_local = |listed: &[u32], own| match listed {
+        [] => vec![own],
+        listed => listed.to_vec(),
+    };
+    let mut times = Vec::new();
+
+    for &hour in &or_local(hours, local.hour()) {
+        for &minute in &or_local(minutes, local.minute()) {
+            for &second in &or_local(seconds, local.second()) {
+                times.extend(NaiveTime::from_hms_nano_opt(
+                    hour,
+                    minute,
+                    second,
+                    local.nanosecond(),
+                ));
+            }
+        }
+    }
+
+    times
+}
+
+/// A well-mixed hash of `x`, see <https://prng.di.unimi.it/splitmix64.c>
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Maps `hash` onto `[0, bound)` by multiplying rather than by a remainder,
+/// which would favor the low end whenever `bound` doesn't divide 2^64
+fn scale(hash: u64, bound: Duration) -> Duration {
+    let bound = u64::try_from(bound.as_nanos()).unwrap_or(u64::MAX);
+    let nanos = (u128::from(hash) * u128::from(bound)) >> 64;
+    Duration::from_nanos(u64::try_from(nanos).expect("bug: nanos is below bound"))
+}
+
+/// Iterates over [`Daily::all`]
+impl IntoIterator for &Daily {
+    type Item = SystemTime;
+    type IntoIter = RecurrenceIter;
+
+    fn into_iter(self) -> RecurrenceIter {
+        self.dates().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use approx::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn starts_today() {
+        let now = SystemTime::now();
+        let dates = super::Daily::new(Options {
+            timezone: Some(chrono_tz::UTC.into()),
+            ..Options::default()
+        });
+        let mut dates = dates.all();
+
+        assert_abs_diff_eq!(
+            now.duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            dates
+                .next()
+                .unwrap()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        );
+    }
+
+    #[cfg(feature = "local-tz")]
+    #[test]
+    fn unknown_local_timezone() {
+        crate::tz_date_iterator::FAIL_LOCAL_TZ.with(|fail| fail.set(true));
+
+        let dtstart = july_first();
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            ..Options::default()
+        });
+
+        assert_eq!(TimeZoneSpec::Named(chrono_tz::UTC), dates.timezone);
+        assert_eq!(dtstart, dates.all().next().unwrap());
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    #[test]
+    fn wasm_defaults_to_utc() {
+        let dates = super::Daily::new(Options::default());
+
+        assert_eq!(TimeZoneSpec::Named(chrono_tz::UTC), dates.timezone);
+    }
+
+    #[cfg(not(feature = "local-tz"))]
+    mod without_local_tz {
+        use super::*;
+
+        #[test]
+        fn explicit_timezone() {
+            let dtstart = july_first();
+            let dates = super::Daily::try_new(Options {
+                dtstart: Some(dtstart),
+                timezone: Some(chrono_tz::UTC.into()),
+                ..Options::default()
+            })
+            .unwrap();
+
+            assert_eq!(dtstart, dates.all().next().unwrap());
+        }
+
+        #[test]
+        fn missing_timezone() {
+            let dates = super::Daily::try_new(Options::default());
+
+            assert_eq!(Some(RecurrenceError::MissingTimezone), dates.err());
+        }
+    }
+
+    #[test]
+    fn into_iter() {
+        let dates = super::Daily::utc(july_first(), 2, End::Count(3));
+
+        let mut visited = vec![];
+        for date in &dates {
+            visited.push(date);
+        }
+
+        assert_eq!(dates.all().collect::<Vec<_>>(), visited);
+    }
+
+    #[test]
+    fn dtstart() {
+        let dtstart = july_first();
+
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            ..Options::default()
+        });
+
+        let first = dates.all().next().unwrap();
+
+        assert_eq!(dtstart, first);
+    }
+
+    #[test]
+    fn dtstart_before_epoch() {
+        let dtstart = SystemTime::UNIX_EPOCH - 36 * ONE_HOUR - Duration::from_millis(500);
+
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            ..Options::default()
+        });
+        let mut dates = dates.all();
+
+        assert_eq!(dtstart, dates.next().unwrap());
+        assert_eq!(dtstart + ONE_DAY, dates.next().unwrap());
+        assert_eq!(dtstart + 2 * ONE_DAY, dates.next().unwrap());
+    }
+
+    #[test]
+    fn across_the_epoch() {
+        let eastern = chrono_tz::America::New_York;
+        let nine_am = |day| {
+            // 1969-12-29 to 1970-01-03, 14:00 UTC each
+            let date = eastern.with_ymd_and_hms(1969, 12, 29, 9, 0, 0).unwrap()
+                + chrono::Duration::days(day);
+            from_timestamp(date.timestamp())
+        };
+        let dates = super::Daily::new(Options {
+            dtstart: Some(nine_am(0)),
+            timezone: Some(eastern.into()),
+            end: End::Count(6),
+            ..Options::default()
+        });
+
+        let expected: Vec<_> = (0..6).map(nine_am).collect();
+        assert_eq!(
+            SystemTime::UNIX_EPOCH - 3 * ONE_DAY + 14 * ONE_HOUR,
+            expected[0]
+        );
+        assert_eq!(expected, dates.all().collect::<Vec<_>>());
+        assert!(dates
+            .all_bounded()
+            .unwrap()
+            .rev()
+            .eq(expected.iter().rev().copied()));
+        assert!(dates
+            .after(SystemTime::UNIX_EPOCH)
+            .eq(expected[3..].iter().copied()));
+    }
+
+    #[test]
+    fn date_out_of_range() {
+        // about 35 million years, well past what chrono can represent
+        let far = Duration::from_secs(1 << 50);
+        let utc = |dtstart, end| {
+            super::Daily::try_new(Options {
+                dtstart: Some(dtstart),
+                timezone: Some(chrono_tz::UTC.into()),
+                end,
+                ..Options::default()
+            })
+        };
+
+        for dtstart in [SystemTime::UNIX_EPOCH + far, SystemTime::UNIX_EPOCH - far] {
+            assert_eq!(
+                Some(RecurrenceError::DateOutOfRange),
+                utc(dtstart, End::Never).err()
+            );
+        }
+        assert_eq!(
+            Some(RecurrenceError::DateOutOfRange),
+            utc(july_first(), End::Until(SystemTime::UNIX_EPOCH + far)).err()
+        );
+
+        let dates = utc(july_first(), End::Count(3)).unwrap();
+        assert_eq!(0, dates.after(SystemTime::UNIX_EPOCH + far).count());
+        assert!(dates.after(SystemTime::UNIX_EPOCH - far).eq(dates.all()));
+    }
+
+    #[test]
+    fn multiple_days() {
+        let dtstart = july_first();
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            ..Options::default()
+        });
+        let mut dates = dates.all().skip(1);
+
+        assert_eq!(dtstart + ONE_DAY, dates.next().unwrap());
+        assert_eq!(dtstart + 2 * ONE_DAY, dates.next().unwrap());
+    }
+
+    #[test]
+    fn by_hour() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 7, 1, 9, 30, 15)
+            .unwrap();
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(4),
+            by_hour: vec![17, 9],
+            ..Options::default()
+        });
+        let times: Vec<_> = dates
+            .all_local()
+            .map(|date| (date.day(), date.hour(), date.minute(), date.second()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (1, 9, 30, 15),
+                (1, 17, 30, 15),
+                (2, 9, 30, 15),
+                (2, 17, 30, 15)
+            ],
+            times
+        );
+        let forward: Vec<_> = dates.all().collect();
+        assert!(dates
+            .all_bounded()
+            .unwrap()
+            .rev()
+            .eq(forward.into_iter().rev()));
+        let evening = SystemTime::from(dtstart) + 8 * ONE_HOUR;
+        assert_eq!(Some((1, evening)), dates.enumerate_after(evening).next());
+        assert_eq!(Some(evening), dates.previous_before(evening + ONE_HOUR));
+
+        // dtstart past the last hour listed moves to the first one the day after
+        let late = super::Daily::new(Options {
+            dtstart: Some((dtstart + chrono::Duration::hours(10)).into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            by_hour: vec![9, 17],
+            ..Options::default()
+        });
+        assert_eq!(Some(SystemTime::from(dtstart) + ONE_DAY), late.all().next());
+
+        let invalid = super::Daily::try_new(Options {
+            by_hour: vec![9, 24],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::InvalidHour(24)), invalid.err());
+    }
+
+    #[test]
+    fn by_hour_and_minute() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 7, 1, 9, 0, 0)
+            .unwrap();
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(6),
+            by_hour: vec![9, 17],
+            by_minute: vec![30, 0],
+            ..Options::default()
+        });
+        let times: Vec<_> = dates
+            .all_local()
+            .map(|date| (date.day(), date.hour(), date.minute()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (1, 9, 0),
+                (1, 9, 30),
+                (1, 17, 0),
+                (1, 17, 30),
+                (2, 9, 0),
+                (2, 9, 30)
+            ],
+            times
+        );
+
+        let seconds = super::Daily::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(3),
+            by_second: vec![15, 45],
+            ..Options::default()
+        });
+        let start = SystemTime::from(dtstart);
+        assert_eq!(
+            vec![
+                start + Duration::from_secs(15),
+                start + Duration::from_secs(45),
+                start + ONE_DAY + Duration::from_secs(15)
+            ],
+            seconds.all().collect::<Vec<_>>()
+        );
+
+        let invalid = super::Daily::try_new(Options {
+            by_minute: vec![60],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::InvalidMinute(60)), invalid.err());
+        let invalid = super::Daily::try_new(Options {
+            by_second: vec![0, 60],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::InvalidSecond(60)), invalid.err());
+    }
+
+    #[test]
+    fn by_hour_across_spring_forward() {
+        // 02:30 doesn't exist on 2019-03-10 in New York
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 3, 9, 2, 30, 0)
+            .unwrap();
+        let hours = |dst_nonexistent| -> Vec<_> {
+            super::Daily::new(Options {
+                dtstart: Some(dtstart.into()),
+                timezone: Some(chrono_tz::US::Eastern.into()),
+                end: End::Count(4),
+                by_hour: vec![2, 9],
+                dst_nonexistent,
+                ..Options::default()
+            })
+            .all_local()
+            .map(|date| (date.day(), date.hour()))
+            .collect()
+        };
+
+        assert_eq!(
+            vec![(9, 2), (9, 9), (10, 3), (10, 9)],
+            hours(NonexistentPolicy::ShiftForward)
+        );
+        assert_eq!(
+            vec![(9, 2), (9, 9), (10, 9), (11, 2)],
+            hours(NonexistentPolicy::Skip)
+        );
+    }
+
+    #[test]
+    fn count_limit() {
+        let dates = super::Daily::new(Options {
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(2),
+            ..Options::default()
+        });
+        let count = dates.all().count();
+        assert_eq!(2, count);
+    }
+
+    #[test]
+    fn until_limit() {
+        let dates = super::Daily::new(Options {
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Until(SystemTime::now() + 5 * ONE_DAY + ONE_MINUTE),
+            ..Options::default()
+        });
+
+        let count = dates.all().count();
+
+        assert_eq!(6, count);
+    }
+
+    #[test]
+    fn clone() {
+        let dates = super::Daily::utc(july_first(), 2, End::Count(3));
+        let cloned = dates.clone();
+
+        assert_eq!(
+            dates.all().collect::<Vec<_>>(),
+            cloned.all().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn debug() {
+        let dates = super::Daily::utc(july_first(), 3, End::Count(2));
+        let debug = format!("{:?}", dates);
+
+        assert!(debug.contains("interval: 3"));
+        assert!(debug.contains("Count(2)"));
+    }
+
+    #[test]
+    fn equality() {
+        let dates = |interval| {
+            super::Daily::new(Options {
+                dtstart: Some(july_first()),
+                interval: Some(interval),
+                timezone: Some(chrono_tz::UTC.into()),
+                end: End::Until(july_first() + 10 * ONE_WEEK),
+                ..Options::default()
+            })
+        };
+
+        assert_eq!(dates(2), dates(2));
+        assert_ne!(dates(2), dates(3));
+    }
+
+    #[test]
+    fn size_hint() {
+        let dates = super::Daily::utc(july_first(), 1, End::Count(5));
+        let mut all = dates.all();
+        assert_eq!((5, Some(5)), all.size_hint());
+        all.next();
+        assert_eq!((4, Some(4)), all.size_hint());
+
+        let never = super::Daily::utc(july_first(), 1, End::Never);
+        assert_eq!((usize::MAX, None), never.all().size_hint());
+
+        let erroring = super::Daily::new(Options {
+            dtstart: Some(july_first()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            end: End::Count(5),
+            dst_nonexistent: NonexistentPolicy::Error,
+            ..Options::default()
+        });
+        assert_eq!((0, Some(5)), erroring.all().size_hint());
+    }
+
+    #[test]
+    fn all_counted() {
+        let dates = super::Daily::utc(july_first(), 1, End::Count(3));
+        let mut counted = dates.all_counted().unwrap();
+        assert_eq!((3, Some(3)), counted.size_hint());
+        counted.next_back();
+        assert_eq!((2, Some(2)), counted.size_hint());
+        assert!(counted.eq(dates.all().take(2)));
+
+        // more days than chrono can represent
+        let too_many = super::Daily::utc(july_first(), 1, End::Count(200_000_000));
+        assert_eq!(
+            (0, Some(200_000_000)),
+            too_many.all_counted().unwrap().size_hint()
+        );
+
+        let never = super::Daily::utc(july_first(), 1, End::Never);
+        assert!(never.all_counted().is_none());
+    }
+
+    #[test]
+    fn first_n() {
+        let dtstart = july_first();
+        let dates = super::Daily::utc(dtstart, 1, End::Count(2));
+        assert_eq!(vec![dtstart, dtstart + ONE_DAY], dates.first_n(5));
+
+        let dates = super::Daily::utc(dtstart, 1, End::Never);
+        assert_eq!(5, dates.first_n(5).len());
+        assert_eq!(
+            vec![dtstart + 2 * ONE_DAY, dtstart + 3 * ONE_DAY],
+            dates.after_n(dtstart + ONE_DAY + ONE_MINUTE, 2)
+        );
+    }
+
+    #[test]
+    fn collect_capped() {
+        let never = super::Daily::utc(july_first(), 1, End::Never);
+        let (dates, truncated) = never.collect_capped(100);
+        assert_eq!(100, dates.len());
+        assert!(truncated);
+
+        let count = super::Daily::utc(july_first(), 1, End::Count(100));
+        let (dates, truncated) = count.collect_capped(100);
+        assert_eq!(100, dates.len());
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn in_year() {
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 12, 31, 23, 30, 0)
+            .unwrap();
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            ..Options::default()
+        });
+
+        // the year is bounded in Eastern time, not UTC
+        assert_eq!(1, dates.in_year(2019).count());
+        assert_eq!(366, dates.in_year(2020).count());
+        assert_eq!(0, dates.in_year(i32::MAX).count());
+        assert_eq!(0, dates.in_year(i32::MIN).count());
+    }
+
+    #[test]
+    fn utc() {
+        let dtstart = july_first();
+
+        let dates = super::Daily::utc(dtstart, 2, End::Count(3));
+        let expected = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            interval: Some(2),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(3),
+            ..Options::default()
+        });
+
+        assert_eq!(
+            expected.all().collect::<Vec<_>>(),
+            dates.all().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn until_boundary() {
+        let dtstart = july_first();
+        let dates = |end| {
+            super::Daily::new(Options {
+                dtstart: Some(dtstart),
+                timezone: Some(chrono_tz::UTC.into()),
+                end,
+                ..Options::default()
+            })
+        };
+
+        let exclusive = dates(End::Until(dtstart + 2 * ONE_DAY));
+        assert_eq!(2, exclusive.all().count());
+
+        let inclusive = dates(End::UntilInclusive(dtstart + 2 * ONE_DAY));
+        assert_eq!(3, inclusive.all().count());
+        assert_eq!(dtstart + 2 * ONE_DAY, inclusive.all().last().unwrap());
+    }
+
+    #[test]
+    fn count_or_until() {
+        let dtstart = july_first();
+        let dates = |count, until| {
+            super::Daily::new(Options {
+                dtstart: Some(dtstart),
+                timezone: Some(chrono_tz::UTC.into()),
+                end: End::CountOrUntil { count, until },
+                ..Options::default()
+            })
+        };
+
+        // the count runs out first
+        assert_eq!(3, dates(3, dtstart + 10 * ONE_DAY).all().count());
+        // the until comes first
+        assert_eq!(
+            5,
+            dates(10, dtstart + 4 * ONE_DAY + ONE_MINUTE).all().count()
+        );
+
+        // skipping occurrences uses up the count
+        let after = dates(3, dtstart + 10 * ONE_DAY).after(dtstart + ONE_DAY);
+        assert_eq!(2, after.count());
+    }
+
+    #[test]
+    fn builder() {
+        let dtstart = july_first();
+
+        let dates = super::Daily::builder()
+            .dtstart(dtstart)
+            .timezone(chrono_tz::UTC)
+            .count(10)
+            .until(dtstart + 2 * ONE_DAY + ONE_MINUTE)
+            .build()
+            .unwrap();
+        assert_eq!(3, dates.all().count());
+
+        let zero = super::Daily::builder()
+            .timezone(chrono_tz::UTC)
+            .interval(0)
+            .build();
+        assert_eq!(Some(RecurrenceError::ZeroInterval), zero.err());
+    }
+
+    #[test]
+    fn dtstart_timestamp() {
+        let after_epoch = super::Daily::builder()
+            .dtstart_timestamp(1_593_576_285)
+            .timezone(chrono_tz::UTC)
+            .build()
+            .unwrap();
+        assert_eq!(july_first(), after_epoch.all().next().unwrap());
+
+        let before_epoch = super::Daily::builder()
+            .dtstart_timestamp(-90_000)
+            .timezone(chrono_tz::UTC)
+            .build()
+            .unwrap();
+        let mut dates = before_epoch.all();
+        assert_eq!(
+            SystemTime::UNIX_EPOCH - ONE_DAY - ONE_HOUR,
+            dates.next().unwrap()
+        );
+        assert_eq!(SystemTime::UNIX_EPOCH - ONE_HOUR, dates.next().unwrap());
+    }
+
+    #[test]
+    fn zero_interval() {
+        let dates = super::Daily::try_new(Options {
+            interval: Some(0),
+            ..Options::default()
+        });
+
+        assert_eq!(Some(RecurrenceError::ZeroInterval), dates.err());
+    }
+
+    #[test]
+    fn interval_too_large() {
+        let dates = super::Daily::try_new(Options {
+            interval: Some(u64::from(u32::MAX) * 100_000),
+            ..Options::default()
+        });
+
+        assert_eq!(Some(RecurrenceError::IntervalTooLarge), dates.err());
+    }
+
+    #[test]
+    fn until_before_start() {
+        let dtstart = july_first();
+
+        let dates = super::Daily::try_new(Options {
+            dtstart: Some(dtstart),
+            end: End::Until(dtstart - ONE_DAY),
+            ..Options::default()
+        });
+
+        assert_eq!(Some(RecurrenceError::UntilBeforeStart), dates.err());
+    }
+
+    #[test]
+    fn after_duration() {
+        let dtstart = july_first();
+
+        let dates = super::Daily::utc(dtstart, 1, End::AfterDuration(ONE_WEEK));
+        assert_eq!(7, dates.all().count());
+        assert_eq!(End::Until(dtstart + ONE_WEEK), dates.to_options().end);
+
+        // the same instant, but kept when an occurrence lands on it
+        let dates = super::Daily::utc(dtstart, 1, End::UntilInclusive(dtstart + ONE_WEEK));
+        assert_eq!(8, dates.all().count());
+    }
+
+    #[test]
+    fn until_at_start() {
+        let dtstart = july_first();
+
+        let dates = super::Daily::try_new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Until(dtstart),
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::UntilBeforeStart), dates.err());
+
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::UntilInclusive(dtstart),
+            ..Options::default()
+        });
+        assert_eq!(vec![dtstart], dates.all().collect::<Vec<_>>());
+
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(0),
+            ..Options::default()
+        });
+        assert_eq!(None, dates.all().next());
+    }
+
+    #[test]
+    fn ends_at_the_last_representable_date() {
+        let last = NaiveDateTime::MAX.date().and_hms_opt(0, 0, 0).unwrap();
+        let dtstart = from_naive_to_system(last - chrono::Duration::days(2));
+        let dates = super::Daily::utc(dtstart, 1, End::Never);
+
+        assert_eq!(3, dates.all().count());
+        assert_eq!(2, super::Daily::utc(dtstart, 2, End::Never).all().count());
+    }
+
+    #[test]
+    fn timezone_name() {
+        let dates = super::Daily::builder()
+            .timezone_name("Europe/Paris")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            TimeZoneSpec::Named(chrono_tz::Europe::Paris),
+            dates.timezone
+        );
+
+        let dates = super::Daily::builder()
+            .timezone_name("Europe/Pariss")
+            .build();
+
+        assert_eq!(
+            Some(RecurrenceError::UnknownTimezone("Europe/Pariss".to_owned())),
+            dates.err()
+        );
+    }
+
+    #[test]
+    fn interval() {
+        let dtstart = july_first();
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            interval: Some(3),
+            ..Options::default()
+        });
+
+        let three_days_later = dates.all().nth(1).unwrap();
+        assert_eq!(dtstart + 3 * ONE_DAY, three_days_later);
+    }
+
+    #[test]
+    fn dst_changes() {
+        let last_day_of_dst = SystemTime::from(
+            chrono_tz::US::Eastern
+                .with_ymd_and_hms(2019, 11, 2, 23, 0, 0)
+                .unwrap(),
+        );
+
+        let dates = super::Daily::new(Options {
+            dtstart: Some(last_day_of_dst),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            ..Options::default()
+        });
+
+        let first_day_of_no_dst = dates.all().nth(1).unwrap();
+        assert_eq!(last_day_of_dst + ONE_DAY + ONE_HOUR, first_day_of_no_dst);
+    }
+
+    #[test]
+    fn estimated_count() {
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 3, 1, 2, 30, 0)
+            .unwrap();
+        let around_dst = [(2019, 3, 9), (2019, 11, 2)];
+
+        for &(year, month, day) in &around_dst {
+            let from = chrono_tz::US::Eastern
+                .with_ymd_and_hms(year, month, day, 0, 0, 0)
+                .unwrap();
+            // every quarter hour over three days, so the until lands on both
+            // sides of each occurrence and inside the skipped/repeated hour
+            for quarter in 0..(3 * 24 * 4) {
+                let until = SystemTime::from(from) + quarter * 15 * ONE_MINUTE;
+
+                for &end in &[End::Until(until), End::UntilInclusive(until)] {
+                    let dates = super::Daily::new(Options {
+                        dtstart: Some(dtstart.into()),
+                        timezone: Some(chrono_tz::US::Eastern.into()),
+                        end,
+                        ..Options::default()
+                    });
+
+                    assert_eq!(
+                        Some(dates.all().count()),
+                        dates.estimated_count(),
+                        "{:?}",
+                        end
+                    );
+                }
+            }
         }
+
+        let never = super::Daily::utc(july_first(), 1, End::Never);
+        assert_eq!(None, never.estimated_count());
     }
 
-    pub fn all(&self) -> impl Iterator<Item = SystemTime> {
-        TzDateIterator {
-            end: self.end.into(),
-            cursor: self.timezone.from_utc_datetime(&self.dtstart),
-            interval: chrono::Duration::days(self.interval as i64),
+    #[test]
+    fn nth_jumps_like_walking() {
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 3, 1, 2, 30, 0)
+            .unwrap();
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            end: End::Count(300),
+            ..Options::default()
+        });
+        let rule = crate::RRule::Daily(dates);
+
+        let walked: Vec<_> = rule.all().collect();
+        for n in 0..310 {
+            assert_eq!(walked.get(n).copied(), rule.all().nth(n), "{}", n);
         }
     }
 
-    pub fn after(&self, min: SystemTime) -> impl Iterator<Item = SystemTime> {
-        let min = self.timezone.from_utc_datetime(&from_system_to_naive(min));
-        let dtstart = self.timezone.from_utc_datetime(&self.dtstart);
-        let mut end = self.end;
+    #[test]
+    fn nanosecond_precision() {
+        // 02:30 falls in the gap when Paris springs forward on 2021-03-28
+        let dtstart = chrono_tz::Europe::Paris
+            .with_ymd_and_hms(2021, 3, 1, 2, 30, 0)
+            .unwrap()
+            .with_nanosecond(123_456_789)
+            .unwrap();
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::Europe::Paris.into()),
+            end: End::Count(1000),
+            ..Options::default()
+        });
+        let nanos = |date: SystemTime| chrono::DateTime::<chrono::Utc>::from(date).nanosecond();
 
-        let cursor = if min <= dtstart {
-            dtstart
-        } else {
-            let time = dtstart.time();
-            let start_date = dtstart.date();
-            let mut date = min.date();
+        assert_eq!(1000, dates.all().count());
+        assert!(dates.all().all(|date| nanos(date) == 123_456_789));
+        assert!(dates
+            .all_bounded()
+            .unwrap()
+            .rev()
+            .all(|date| nanos(date) == 123_456_789));
 
-            if time < min.time() {
-                date = date.succ();
-            }
+        let middle = dates.all().nth(500).unwrap();
+        assert_eq!(Some(middle), dates.after(middle).next());
+        assert_eq!(Some(middle), dates.after(middle - ONE_HOUR).next());
+        assert_eq!(Some(middle), crate::RRule::Daily(dates).all().nth(500));
+    }
 
-            if let End::Count(ref mut c) = end {
-                *c = c.saturating_sub((date - start_date).num_days() as usize);
-            }
+    #[test]
+    fn shifted() {
+        let dtstart = july_first();
+        let dates = super::Daily::utc(dtstart, 1, End::Count(3));
 
-            date.and_time(time).expect("bug: and_time")
-        };
+        let reminders: Vec<_> = dates.shifted(chrono::Duration::minutes(-15)).collect();
+        let expected: Vec<_> = dates.all().map(|date| date - 15 * ONE_MINUTE).collect();
+
+        assert_eq!(expected, reminders);
+        assert_eq!(dtstart - 15 * ONE_MINUTE, reminders[0]);
+    }
 
-        TzDateIterator {
-            end: end.into(),
-            interval: chrono::Duration::days(self.interval as i64),
-            cursor,
+    #[test]
+    fn filter_weekday() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 3, 1, 9, 0, 0)
+            .unwrap();
+        let dates = super::Daily::utc(dtstart.into(), 1, End::Count(31));
+
+        let weekends: Vec<_> = dates
+            .filter_weekday(&[Weekday::Sat, Weekday::Sun])
+            .map(|date| DateTime::<chrono::Utc>::from(date).day())
+            .collect();
+
+        assert_eq!(vec![6, 7, 13, 14, 20, 21, 27, 28], weekends);
+    }
+
+    #[test]
+    fn jittered() {
+        let dates = super::Daily::utc(july_first(), 1, End::Count(20));
+        let max = 10 * ONE_MINUTE;
+
+        let first: Vec<_> = dates.jittered(max, 42).collect();
+        let again: Vec<_> = dates.jittered(max, 42).collect();
+        let other_seed: Vec<_> = dates.jittered(max, 43).collect();
+
+        assert_eq!(first, again);
+        assert_ne!(first, other_seed);
+        for (jittered, date) in first.iter().zip(dates.all()) {
+            let offset = jittered.duration_since(date).unwrap();
+            assert!(offset < max, "{:?}", offset);
+        }
+
+        // a day apart, so never jittered past the next day but the last
+        let spread: Vec<_> = dates.jittered(3 * ONE_DAY, 42).collect();
+        for (jittered, date) in spread.iter().zip(dates.all()).take(19) {
+            let offset = jittered.duration_since(date).unwrap();
+            assert!(offset < ONE_DAY, "{:?}", offset);
         }
+        assert!(spread.windows(2).all(|pair| pair[0] < pair[1]));
     }
-}
 
-fn from_system_to_naive(time: SystemTime) -> NaiveDateTime {
-    let duration = time.duration_since(SystemTime::UNIX_EPOCH).expect("bug");
-    NaiveDateTime::from_timestamp(duration.as_secs() as i64, duration.subsec_nanos())
-}
+    #[test]
+    fn after_lands_on_an_interval_multiple() {
+        let dtstart = july_first();
+        let dates = super::Daily::utc(dtstart, 3, End::Never);
 
-fn local_tz() -> Tz {
-    iana_time_zone::get_timezone()
-        .expect("bug: could not get tz")
-        .parse()
-        .expect("bug: local tz could not be parsed")
-}
+        for days in 1..7 {
+            let min = dtstart + days * ONE_DAY - ONE_HOUR;
+            let expected = dtstart + days.div_ceil(3) * 3 * ONE_DAY;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::test_helpers::*;
-    use approx::*;
-    use std::time::SystemTime;
+            let mut after = dates.after(min);
+            assert_eq!(Some(expected), after.next(), "{}", days);
+            assert_eq!(Some(expected + 3 * ONE_DAY), after.next(), "{}", days);
+        }
+    }
 
     #[test]
-    fn starts_today() {
-        let now = SystemTime::now();
-        let dates = super::Daily::new(Options::default());
-        let mut dates = dates.all();
+    fn next_after() {
+        let dtstart = july_first();
+        let dates = super::Daily::utc(dtstart, 2, End::Count(3));
 
-        assert_abs_diff_eq!(
-            now.duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            dates
-                .next()
-                .unwrap()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+        assert_eq!(Some(dtstart + 2 * ONE_DAY), dates.next_after(dtstart));
+        assert_eq!(Some(dtstart), dates.next_after(dtstart - ONE_MINUTE));
+        assert_eq!(
+            Some(dtstart + 4 * ONE_DAY),
+            dates.next_after(dtstart + 3 * ONE_DAY)
         );
+        assert_eq!(None, dates.next_after(dtstart + 4 * ONE_DAY));
     }
 
     #[test]
-    fn dtstart() {
+    fn is_occurrence() {
+        // 02:30 doesn't exist on 2019-03-10 so that occurrence shifts to 03:30
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 3, 9, 2, 30, 0)
+            .unwrap()
+            .with_nanosecond(500)
+            .unwrap();
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            ..Options::default()
+        });
+        let shifted = SystemTime::from(dtstart) + ONE_DAY;
+        let nanosecond = Duration::from_nanos(1);
+
+        assert!(dates.is_occurrence(dtstart.into()));
+        assert!(dates.is_occurrence(shifted));
+        assert!(!dates.is_occurrence(shifted - nanosecond));
+        assert!(!dates.is_occurrence(shifted + nanosecond));
+        assert!(!dates.is_occurrence(shifted - ONE_HOUR));
+    }
+
+    #[test]
+    fn previous_before() {
+        let dtstart = july_first();
+        let dates = super::Daily::utc(dtstart, 2, End::Count(3));
+
+        assert_eq!(
+            Some(dtstart + 2 * ONE_DAY),
+            dates.previous_before(dtstart + 3 * ONE_DAY)
+        );
+        assert_eq!(Some(dtstart), dates.previous_before(dtstart + 2 * ONE_DAY));
+        assert_eq!(None, dates.previous_before(dtstart));
+        // nothing past the third occurrence
+        assert_eq!(
+            Some(dtstart + 4 * ONE_DAY),
+            dates.previous_before(dtstart + ONE_WEEK)
+        );
+
+        let until = super::Daily::utc(dtstart, 1, End::Until(dtstart + 3 * ONE_DAY));
+        assert_eq!(
+            Some(dtstart + 2 * ONE_DAY),
+            until.previous_before(dtstart + ONE_WEEK)
+        );
+
+        // clocks spring forward past 02:30 on 2019-03-10
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 3, 1, 2, 30, 0)
+            .unwrap();
+        let dst = super::Daily::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            end: End::Count(20),
+            ..Options::default()
+        });
+        for hours in 0..24 * 25 {
+            let t = SystemTime::from(dtstart) + hours * ONE_HOUR;
+            let walked = dst.all().take_while(|&date| date < t).last();
+            assert_eq!(walked, dst.previous_before(t), "{}", hours);
+        }
+    }
+
+    #[test]
+    fn after_exact_occurrence() {
         let dtstart = july_first();
+        let dates = super::Daily::utc(dtstart, 2, End::Never);
+
+        assert_eq!(Some(dtstart), dates.after(dtstart).next());
+        assert_eq!(
+            Some(dtstart + 4 * ONE_DAY),
+            dates.after(dtstart + 4 * ONE_DAY).next()
+        );
+    }
 
+    #[test]
+    fn after_exact_occurrence_moved_by_dst() {
+        // 02:30 doesn't exist on 2019-03-10 and is shifted to 03:30
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 3, 8, 2, 30, 0)
+            .unwrap();
         let dates = super::Daily::new(Options {
-            dtstart: Some(dtstart),
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
             ..Options::default()
         });
+        let shifted = dates.all().nth(2).unwrap();
 
-        let first = dates.all().nth(0).unwrap();
+        assert_eq!(Some(shifted), dates.after(shifted).next());
 
-        assert_eq!(dtstart, first);
+        // 01:30 happens twice on 2019-11-03 and the first one is picked
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 11, 1, 1, 30, 0)
+            .unwrap();
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            ..Options::default()
+        });
+        let repeated = dates.all().nth(2).unwrap();
+        let next = dates.all().nth(3).unwrap();
+
+        // the repeated 01:15 comes after the first 01:30
+        assert_eq!(Some(next), dates.after(repeated + 45 * ONE_MINUTE).next());
     }
 
     #[test]
-    fn multiple_days() {
-        let dtstart = july_first();
+    fn describe() {
+        let daily = super::Daily::utc(july_first(), 1, End::Count(1));
+        let weekdays = super::Daily::builder()
+            .timezone(chrono_tz::UTC)
+            .skip_weekends()
+            .holidays(vec![NaiveDate::from_ymd_opt(2020, 12, 25).unwrap()])
+            .count(10)
+            .build()
+            .unwrap();
+        let hours = super::Daily::builder()
+            .timezone(chrono_tz::UTC)
+            .interval_duration(36 * ONE_HOUR)
+            .build()
+            .unwrap();
+
+        assert_eq!("Every day, once.", daily.describe());
+        assert_eq!(
+            "Every weekday except holidays, 10 times.",
+            weekdays.describe()
+        );
+        assert_eq!("Every 36 hours.", hours.describe());
+    }
+
+    #[test]
+    fn interval_duration() {
+        let local = |day, hour| {
+            SystemTime::from(
+                chrono_tz::US::Eastern
+                    .with_ymd_and_hms(2020, 3, day, hour, 0, 0)
+                    .unwrap(),
+            )
+        };
+
+        let dates = super::Daily::builder()
+            .dtstart(local(7, 9))
+            .timezone(chrono_tz::US::Eastern)
+            .interval_duration(36 * ONE_HOUR)
+            .build()
+            .unwrap();
+
+        // clocks spring forward on the 8th, so the wall-clock time holds
+        // while only 35 hours go by
+        let expected = vec![local(7, 9), local(8, 21), local(10, 9), local(11, 21)];
+        assert_eq!(expected, dates.first_n(4));
+        assert_eq!(expected[1..], dates.after_n(local(8, 12), 3)[..]);
+
+        // 01:05 EST on the day clocks fall back, whose wall-clock time is
+        // before dtstart's 01:45 EDT
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 11, 3, 1, 45, 0)
+            .earliest()
+            .unwrap();
+        let dates = super::Daily::builder()
+            .zoned_dtstart(dtstart)
+            .interval_duration(20 * ONE_MINUTE)
+            .build()
+            .unwrap();
+        let min = SystemTime::from(dtstart) + 20 * ONE_MINUTE;
+        assert_eq!(
+            dates.all().find(|&date| date >= min),
+            dates.first_after(min)
+        );
+    }
+
+    #[test]
+    fn until_in_repeated_hour() {
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 11, 2, 1, 30, 0)
+            .unwrap();
+        // the second 01:30 on the day clocks fall back
+        let until = SystemTime::from(dtstart) + ONE_DAY + ONE_HOUR;
+
+        let count = |end, ambiguous| {
+            super::Daily::new(Options {
+                dtstart: Some(dtstart.into()),
+                timezone: Some(chrono_tz::US::Eastern.into()),
+                end,
+                dst_ambiguous: ambiguous,
+                ..Options::default()
+            })
+            .all()
+            .count()
+        };
+
+        assert_eq!(2, count(End::Until(until), AmbiguousPolicy::First));
+        assert_eq!(1, count(End::Until(until), AmbiguousPolicy::Second));
+        assert_eq!(
+            2,
+            count(End::UntilInclusive(until), AmbiguousPolicy::Second)
+        );
+    }
+
+    #[test]
+    fn fixed_offset() {
+        let india = chrono::FixedOffset::east_opt(5 * 60 * 60 + 30 * 60).unwrap();
+        let dtstart = SystemTime::from(india.with_ymd_and_hms(2019, 11, 2, 23, 0, 0).unwrap());
+
         let dates = super::Daily::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(india.into()),
             ..Options::default()
         });
-        let mut dates = dates.all().skip(1);
+        let mut dates = dates.all();
 
+        assert_eq!(dtstart, dates.next().unwrap());
         assert_eq!(dtstart + ONE_DAY, dates.next().unwrap());
         assert_eq!(dtstart + 2 * ONE_DAY, dates.next().unwrap());
     }
 
     #[test]
-    fn count_limit() {
+    fn all_local() {
+        let last_day_of_dst = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 11, 2, 23, 0, 0)
+            .unwrap();
+
         let dates = super::Daily::new(Options {
-            end: End::Count(2),
+            dtstart: Some(last_day_of_dst.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
             ..Options::default()
         });
-        let count = dates.all().count();
-        assert_eq!(2, count);
+        let mut dates = dates.all_local();
+
+        let first = dates.next().unwrap();
+        let second = dates.next().unwrap();
+
+        assert_eq!(23, first.hour());
+        assert_eq!(23, second.hour());
+        assert_eq!(chrono::Duration::hours(25), second - first);
     }
 
     #[test]
-    fn until_limit() {
+    fn skip_weekends() {
+        // a Wednesday
+        let dtstart = july_first();
+        let dates = super::Daily::builder()
+            .dtstart(dtstart)
+            .timezone(chrono_tz::UTC)
+            .skip_weekends()
+            .until(dtstart + ONE_WEEK)
+            .build()
+            .unwrap();
+
+        let business_days = [0, 1, 2, 5, 6].map(|days| dtstart + days * ONE_DAY);
+        assert_eq!(business_days[..], dates.all().collect::<Vec<_>>()[..]);
+        assert_eq!(
+            business_days[1..],
+            dates.after(dtstart + ONE_HOUR).collect::<Vec<_>>()[..]
+        );
+        assert_eq!(
+            business_days.iter().rev().copied().collect::<Vec<_>>(),
+            dates.all_bounded().unwrap().rev().collect::<Vec<_>>()
+        );
+
+        // every other business day, starting on a Saturday
+        let dates = super::Daily::builder()
+            .dtstart(dtstart + 3 * ONE_DAY)
+            .timezone(chrono_tz::UTC)
+            .skip_weekends()
+            .interval(2)
+            .count(3)
+            .build()
+            .unwrap();
+
+        let expected = [5, 7, 9].map(|days| dtstart + days * ONE_DAY);
+        assert_eq!(expected[..], dates.all().collect::<Vec<_>>()[..]);
+    }
+
+    #[test]
+    fn holidays() {
+        // Monday the 6th through Friday the 10th, with a holiday on Wednesday
+        let monday = july_first() + 5 * ONE_DAY;
+        let holiday = NaiveDate::from_ymd_opt(2020, 7, 8).unwrap();
+        let dates = super::Daily::builder()
+            .dtstart(monday)
+            .timezone(chrono_tz::UTC)
+            .skip_weekends()
+            .holidays([holiday])
+            .until(monday + ONE_WEEK)
+            .build()
+            .unwrap();
+
+        let working_days = [0, 1, 3, 4].map(|days| monday + days * ONE_DAY);
+        assert_eq!(working_days[..], dates.all().collect::<Vec<_>>()[..]);
+
+        // the holiday doesn't count towards the interval either
+        let dates = super::Daily::builder()
+            .dtstart(monday)
+            .timezone(chrono_tz::UTC)
+            .skip_weekends()
+            .holidays([holiday])
+            .interval(2)
+            .count(3)
+            .build()
+            .unwrap();
+
+        let expected = [0, 3, 7].map(|days| monday + days * ONE_DAY);
+        assert_eq!(expected[..], dates.all().collect::<Vec<_>>()[..]);
+    }
+
+    #[test]
+    fn holidays_across_weekends() {
+        // the weeks of Christmas and New Year's off, from Monday 2020-12-21
+        let monday = chrono_tz::UTC
+            .with_ymd_and_hms(2020, 12, 21, 9, 0, 0)
+            .unwrap();
+        let holidays = (21..26)
+            .chain(28..32)
+            .map(|day| NaiveDate::from_ymd_opt(2020, 12, day).unwrap())
+            .chain(NaiveDate::from_ymd_opt(2021, 1, 1));
+        let dates = super::Daily::builder()
+            .dtstart(monday.into())
+            .timezone(chrono_tz::UTC)
+            .skip_weekends()
+            .holidays(holidays)
+            .count(1)
+            .build()
+            .unwrap();
+
+        let back = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 1, 4, 9, 0, 0)
+            .unwrap();
+        assert_eq!(
+            vec![SystemTime::from(back)],
+            dates.all().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn all_in() {
+        let dtstart = chrono_tz::America::New_York
+            .with_ymd_and_hms(2020, 3, 7, 9, 0, 0)
+            .unwrap();
+        let dates = super::Daily::builder()
+            .zoned_dtstart(dtstart)
+            .count(3)
+            .build()
+            .unwrap();
+
+        let shown: Vec<_> = dates.all_in(chrono_tz::Europe::London).collect();
+
+        assert_eq!(
+            dates.all().collect::<Vec<_>>(),
+            shown
+                .iter()
+                .map(|&date| date.into())
+                .collect::<Vec<SystemTime>>()
+        );
+        // New York springs forward on the 8th but London only weeks later
+        let hours: Vec<_> = shown.iter().map(|date| date.hour()).collect();
+        assert_eq!(vec![14, 13, 13], hours);
+        assert_eq!(chrono_tz::Europe::London, shown[0].timezone());
+    }
+
+    #[test]
+    fn all_with_duration() {
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 3, 9, 1, 30, 0)
+            .unwrap();
+
+        let dates = super::Daily::builder()
+            .zoned_dtstart(dtstart)
+            .duration(ONE_HOUR)
+            .build()
+            .unwrap();
+        let mut dates = dates.all_with_duration();
+
+        let (start, end) = dates.next().unwrap();
+        assert_eq!(SystemTime::from(dtstart), start);
+        assert_eq!(start + ONE_HOUR, end);
+
+        // clocks spring forward at 02:00 so the event ends at 03:30
+        let (start, end) = dates.next().unwrap();
+        assert_eq!(SystemTime::from(dtstart) + ONE_DAY, start);
+        assert_eq!(start + ONE_HOUR, end);
+        let end = DateTime::<chrono::Utc>::from(end).with_timezone(&chrono_tz::US::Eastern);
+        assert_eq!((3, 30), (end.hour(), end.minute()));
+    }
+
+    #[test]
+    fn all_day() {
+        let afternoon = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 11, 2, 15, 0, 0)
+            .unwrap();
+        let midnight = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 11, 2, 0, 0, 0)
+            .unwrap();
+
         let dates = super::Daily::new(Options {
-            end: End::Until(SystemTime::now() + 5 * ONE_DAY + ONE_MINUTE),
+            dtstart: Some(afternoon.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            all_day: true,
             ..Options::default()
         });
+        // rebuilding it from its options keeps it all-day
+        let rebuilt = super::Daily::new(dates.to_options());
+        assert!(rebuilt.all().take(3).eq(dates.all().take(3)));
+        assert!(rebuilt.is_all_day());
+        let dates: Vec<_> = dates.all_local().take(3).collect();
 
-        let count = dates.all().count();
+        assert_eq!(midnight, dates[0]);
+        // the extra hour when clocks fall back keeps the next one at midnight
+        assert_eq!(chrono::Duration::hours(24), dates[1] - dates[0]);
+        assert_eq!(chrono::Duration::hours(25), dates[2] - dates[1]);
+        assert_eq!((3, 0), (dates[1].day(), dates[1].hour()));
+        assert_eq!((4, 0), (dates[2].day(), dates[2].hour()));
+        assert_eq!(
+            TimeZoneSpec::Named(chrono_tz::US::Eastern),
+            dates[2].timezone()
+        );
+    }
 
-        assert_eq!(6, count);
+    #[test]
+    fn dst_ambiguous() {
+        // 01:30 happens twice the next day, when clocks fall back
+        let dtstart = SystemTime::from(
+            chrono_tz::US::Eastern
+                .with_ymd_and_hms(2019, 11, 2, 1, 30, 0)
+                .unwrap(),
+        );
+        let dates = |dst_ambiguous| {
+            super::Daily::new(Options {
+                dtstart: Some(dtstart),
+                timezone: Some(chrono_tz::US::Eastern.into()),
+                dst_ambiguous,
+                ..Options::default()
+            })
+        };
+
+        let first = dates(AmbiguousPolicy::First).all().nth(1).unwrap();
+        assert_eq!(dtstart + ONE_DAY, first);
+
+        let second = dates(AmbiguousPolicy::Second).all().nth(1).unwrap();
+        assert_eq!(dtstart + ONE_DAY + ONE_HOUR, second);
+
+        assert_eq!(1, dates(AmbiguousPolicy::Error).all().count());
     }
 
     #[test]
-    fn interval() {
+    fn dst_nonexistent() {
+        // 02:30 doesn't happen the next day, when clocks spring forward
+        let dtstart = SystemTime::from(
+            chrono_tz::US::Eastern
+                .with_ymd_and_hms(2019, 3, 9, 2, 30, 0)
+                .unwrap(),
+        );
+        let dates = |dst_nonexistent| {
+            super::Daily::new(Options {
+                dtstart: Some(dtstart),
+                timezone: Some(chrono_tz::US::Eastern.into()),
+                dst_nonexistent,
+                ..Options::default()
+            })
+        };
+
+        let mut shifted = dates(NonexistentPolicy::ShiftForward).all().skip(1);
+        // 03:30 EDT is the same instant as 02:30 EST
+        assert_eq!(dtstart + ONE_DAY, shifted.next().unwrap());
+        // and the day after is back at 02:30
+        assert_eq!(dtstart + 2 * ONE_DAY - ONE_HOUR, shifted.next().unwrap());
+
+        let skipped = dates(NonexistentPolicy::Skip).all().nth(1).unwrap();
+        assert_eq!(dtstart + 2 * ONE_DAY - ONE_HOUR, skipped);
+
+        assert_eq!(1, dates(NonexistentPolicy::Error).all().count());
+    }
+
+    #[test]
+    fn rev_until() {
         let dtstart = july_first();
+
         let dates = super::Daily::new(Options {
             dtstart: Some(dtstart),
-            interval: Some(3),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Until(dtstart + 3 * ONE_DAY + ONE_MINUTE),
             ..Options::default()
         });
+        let mut dates = dates.all_bounded().unwrap();
 
-        let three_days_later = dates.all().nth(1).unwrap();
-        assert_eq!(dtstart + 3 * ONE_DAY, three_days_later);
+        assert_eq!(Some(dtstart + 3 * ONE_DAY), dates.next_back());
+        assert_eq!(Some(dtstart), dates.next());
+        assert_eq!(Some(dtstart + 2 * ONE_DAY), dates.next_back());
+        assert_eq!(Some(dtstart + ONE_DAY), dates.next());
+        assert_eq!(None, dates.next_back());
+        assert_eq!(None, dates.next());
+
+        let never = super::Daily::utc(dtstart, 1, End::Never);
+        assert!(never.all_bounded().is_none());
     }
 
     #[test]
-    fn dst_changes() {
-        let last_day_of_dst =
-            SystemTime::from(chrono_tz::US::Eastern.ymd(2019, 11, 2).and_hms(23, 0, 0));
-
+    fn all_csv() {
         let dates = super::Daily::new(Options {
-            dtstart: Some(last_day_of_dst),
-            timezone: Some(chrono_tz::US::Eastern),
+            dtstart: Some(july_first()),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(2),
             ..Options::default()
         });
 
-        let first_day_of_no_dst = dates.all().nth(1).unwrap();
-        assert_eq!(last_day_of_dst + ONE_DAY + ONE_HOUR, first_day_of_no_dst);
+        assert_eq!(
+            "index,iso8601_local,unix_seconds\n\
+             0,2020-07-01T00:04:45-04:00,1593576285\n\
+             1,2020-07-02T00:04:45-04:00,1593662685\n",
+            dates.all_csv(chrono_tz::America::New_York, 5)
+        );
     }
 
     #[test]
@@ -188,10 +2158,11 @@ mod tests {
 
         let dates = super::Daily::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             ..Options::default()
         });
 
-        let first = dates.after(dtstart - 40 * ONE_HOUR).nth(0).unwrap();
+        let first = dates.after(dtstart - 40 * ONE_HOUR).next().unwrap();
         assert_eq!(dtstart, first);
     }
 
@@ -201,6 +2172,7 @@ mod tests {
 
         let dates = super::Daily::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             ..Options::default()
         });
 
@@ -214,12 +2186,13 @@ mod tests {
 
         let dates = super::Daily::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             ..Options::default()
         });
 
         let first = dates
             .after(dtstart + 5 * ONE_DAY + ONE_MINUTE)
-            .nth(0)
+            .next()
             .unwrap();
 
         assert_eq!(dtstart + 6 * ONE_DAY, first);
@@ -231,6 +2204,7 @@ mod tests {
 
         let dates = super::Daily::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             end: End::Count(5),
             ..Options::default()
         });
@@ -242,16 +2216,63 @@ mod tests {
         assert_eq!(1, dates.after(dtstart + 4 * ONE_DAY).count());
     }
 
+    #[test]
+    fn after_with_count_and_interval() {
+        let dtstart = july_first();
+
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            interval: Some(3),
+            end: End::Count(5),
+            ..Options::default()
+        });
+
+        // skipping 6 days only skips the occurrences on day 0 and day 3
+        assert_eq!(3, dates.after(dtstart + 6 * ONE_DAY).count());
+    }
+
     #[test]
     fn after_too_late() {
         let dtstart = july_first();
 
         let dates = super::Daily::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             end: End::Count(2),
             ..Options::default()
         });
 
         assert_eq!(0, dates.after(dtstart + 4 * ONE_DAY).count());
     }
+
+    #[test]
+    fn after_count_exhausted() {
+        let dtstart = july_first();
+
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(1),
+            ..Options::default()
+        });
+
+        assert_eq!(0, dates.after(dtstart + 100 * ONE_DAY).count());
+    }
+
+    #[test]
+    fn enumerate_after() {
+        let dtstart = july_first();
+
+        let dates = super::Daily::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            interval: Some(2),
+            ..Options::default()
+        });
+        let mut dates = dates.enumerate_after(dtstart + 3 * 2 * ONE_DAY);
+
+        assert_eq!((3, dtstart + 6 * ONE_DAY), dates.next().unwrap());
+        assert_eq!((4, dtstart + 8 * ONE_DAY), dates.next().unwrap());
+    }
 }
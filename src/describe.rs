@@ -0,0 +1,60 @@
+//! Pieces of the human-readable rule descriptions, e.g. "Every 2 weeks until
+//! 2025-12-31."
+
+use crate::{tz_date_iterator::from_system_to_naive, End, TimeZoneSpec};
+use chrono::TimeZone as _;
+use std::time::{Duration, SystemTime};
+
+/// "Every week", "Every 2 weeks"
+pub fn every(interval: u64, unit: &str) -> String {
+    match interval {
+        1 => format!("Every {}", unit),
+        n => format!("Every {} {}s", n, unit),
+    }
+}
+
+/// "Every 36 hours", in the largest unit the interval is a whole number of
+pub fn every_duration(interval: Duration) -> String {
+    let secs = interval.as_secs();
+    if secs == 0 || interval.subsec_nanos() != 0 {
+        return format!("Every {:?}", interval);
+    }
+
+    [
+        (86400, "day"),
+        (3600, "hour"),
+        (60, "minute"),
+        (1, "second"),
+    ]
+    .iter()
+    .find(|(unit, _)| secs % unit == 0)
+    .map(|&(unit, name)| every(secs / unit, name))
+    .expect("bug: every number of seconds is a multiple of one")
+}
+
+/// ", 3 times", " until 2025-12-31", or nothing for a rule without an end.
+/// Dates are in `timezone`.
+pub fn end(end: End, timezone: TimeZoneSpec) -> String {
+    let day = |date: SystemTime| {
+        timezone
+            .from_utc_datetime(
+                &from_system_to_naive(date).expect("bug: ends are checked when built"),
+            )
+            .format("%Y-%m-%d")
+            .to_string()
+    };
+    // an exclusive until at midnight reads as the day before it
+    let last_day = |until: SystemTime| day(until - Duration::from_nanos(1));
+
+    match end {
+        End::Never => String::new(),
+        End::Count(1) => ", once".to_owned(),
+        End::Count(count) => format!(", {} times", count),
+        End::Until(until) => format!(" until {}", last_day(until)),
+        End::UntilInclusive(until) => format!(" until {}", day(until)),
+        End::CountOrUntil { count, until } => {
+            format!(", at most {} times, until {}", count, last_day(until))
+        }
+        End::AfterDuration(duration) => format!(" for {:?}", duration),
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{RecurrenceError, TimeZoneSpec};
+use chrono::{
+    DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset as _,
+    TimeZone as _,
+};
+use std::time::SystemTime;
+
+/// Which instant an occurrence lands on when its wall-clock time happens
+/// twice, i.e. during the repeated hour when clocks fall back
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum AmbiguousPolicy {
+    /// The earlier instant, still using the offset from before the change
+    #[default]
+    First,
+    /// The later instant, already using the offset from after the change
+    Second,
+    /// Stop iterating; there is no occurrence that can be picked
+    Error,
+}
+
+/// What happens to an occurrence whose wall-clock time is skipped when
+/// clocks spring forward
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum NonexistentPolicy {
+    /// Move it forward by the length of the gap, e.g. 02:30 becomes 03:30
+    #[default]
+    ShiftForward,
+    /// Leave it out of the series
+    Skip,
+    /// Stop iterating; there is no occurrence that can be picked
+    Error,
+}
+
+/// Where a wall-clock time lands once the DST policies are applied
+pub enum Resolution {
+    At(DateTime<TimeZoneSpec>),
+    Skip,
+    Stop,
+}
+
+/// The instant for the wall-clock time `local` in `timezone`
+pub fn resolve(
+    timezone: TimeZoneSpec,
+    local: NaiveDateTime,
+    ambiguous: AmbiguousPolicy,
+    nonexistent: NonexistentPolicy,
+) -> Resolution {
+    match timezone.from_local_datetime(&local) {
+        LocalResult::Single(date) => Resolution::At(date),
+        LocalResult::Ambiguous(first, second) => match ambiguous {
+            AmbiguousPolicy::First => Resolution::At(first),
+            AmbiguousPolicy::Second => Resolution::At(second),
+            AmbiguousPolicy::Error => Resolution::Stop,
+        },
+        LocalResult::None => match nonexistent {
+            NonexistentPolicy::ShiftForward => Resolution::At(shift_forward(timezone, local)),
+            NonexistentPolicy::Skip => Resolution::Skip,
+            NonexistentPolicy::Error => Resolution::Stop,
+        },
+    }
+}
+
+/// Places a wall-clock time that falls in a gap using the offset from before
+/// the gap, which lands it as far past the gap as it was into it.
+fn shift_forward(timezone: TimeZoneSpec, local: NaiveDateTime) -> DateTime<TimeZoneSpec> {
+    let before = timezone
+        .offset_from_utc_datetime(&(local - Duration::days(1)))
+        .fix();
+
+    timezone.from_utc_datetime(&(local - before))
+}
+
+/// The first instant of `date` in `timezone`, which is midnight unless
+/// clocks spring forward right at it
+pub fn start_of_day(timezone: TimeZoneSpec, date: NaiveDate) -> DateTime<TimeZoneSpec> {
+    let midnight = date.and_time(NaiveTime::MIN);
+
+    match resolve(
+        timezone,
+        midnight,
+        AmbiguousPolicy::First,
+        NonexistentPolicy::ShiftForward,
+    ) {
+        Resolution::At(start) => start,
+        _ => unreachable!("bug: these policies always place the date"),
+    }
+}
+
+/// The same wall-clock time as `dtstart` (in UTC) on the first day from
+/// dtstart's on that is `wanted`, looking at most `within` days ahead. A
+/// time that DST skips or repeats on that day is placed by the default
+/// policies.
+pub fn first_day(
+    timezone: TimeZoneSpec,
+    dtstart: NaiveDateTime,
+    wanted: impl Fn(NaiveDate) -> bool,
+    within: u64,
+) -> Result<NaiveDateTime, RecurrenceError> {
+    let mut local = timezone.from_utc_datetime(&dtstart).naive_local();
+    if wanted(local.date()) {
+        return Ok(dtstart);
+    }
+    for _ in 0..within {
+        local = local
+            .checked_add_signed(Duration::days(1))
+            .ok_or(RecurrenceError::DateOutOfRange)?;
+        if wanted(local.date()) {
+            return Ok(to_utc(timezone, local));
+        }
+    }
+
+    Err(RecurrenceError::NoMatchingDate)
+}
+
+/// The UTC time of the wall-clock time `local`, placed with the default
+/// policies which always pick an instant
+pub fn to_utc(timezone: TimeZoneSpec, local: NaiveDateTime) -> NaiveDateTime {
+    match resolve(
+        timezone,
+        local,
+        AmbiguousPolicy::default(),
+        NonexistentPolicy::default(),
+    ) {
+        Resolution::At(date) => date.naive_utc(),
+        _ => unreachable!("bug: the default policies always place the time"),
+    }
+}
+
+/// Moves `dtstart` (in UTC) to the local midnight starting its day. Stepping
+/// by whole days from there keeps every occurrence on a local midnight, as
+/// long as it is placed by [`ALL_DAY_POLICIES`].
+pub fn all_day(timezone: TimeZoneSpec, dtstart: NaiveDateTime) -> NaiveDateTime {
+    let local = timezone.from_utc_datetime(&dtstart).date_naive();
+    start_of_day(timezone, local).naive_utc()
+}
+
+/// The policies placing all-day occurrences on the first instant of their
+/// day, even when DST skips or repeats its midnight
+pub const ALL_DAY_POLICIES: (AmbiguousPolicy, NonexistentPolicy) =
+    (AmbiguousPolicy::First, NonexistentPolicy::ShiftForward);
+
+/// The instants the UTC offset changed between consecutive `occurrences`,
+/// i.e. the DST transitions that moved an occurrence relative to the one
+/// before it. Transitions that are undone before the next occurrence don't
+/// move anything and are left out.
+pub fn transitions(occurrences: impl Iterator<Item = DateTime<TimeZoneSpec>>) -> Vec<SystemTime> {
+    let mut transitions = vec![];
+    let mut previous: Option<DateTime<TimeZoneSpec>> = None;
+
+    for current in occurrences {
+        if let Some(previous) = previous {
+            if previous.offset().fix() != current.offset().fix() {
+                transitions.push(transition(previous, current));
+            }
+        }
+        previous = Some(current);
+    }
+
+    transitions
+}
+
+/// The first second in `(before, after]` using the offset of `after`,
+/// narrowed down by bisection
+fn transition(before: DateTime<TimeZoneSpec>, after: DateTime<TimeZoneSpec>) -> SystemTime {
+    let timezone = after.timezone();
+    let offset = |secs| {
+        let utc = DateTime::from_timestamp(secs, 0).expect("bug: between two occurrences");
+        timezone.offset_from_utc_datetime(&utc.naive_utc()).fix()
+    };
+
+    let (mut low, mut high) = (before.timestamp(), after.timestamp());
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if offset(middle) == after.offset().fix() {
+            high = middle;
+        } else {
+            low = middle;
+        }
+    }
+
+    crate::tz_date_iterator::from_timestamp(high)
+}
+
+/// The first instants of `year` and of the year after it in `timezone`.
+/// `None` unless chrono can represent the years on either side too, which
+/// leaves room for any UTC offset.
+pub fn year_bounds(timezone: TimeZoneSpec, year: i32) -> Option<(SystemTime, SystemTime)> {
+    let jan_first = |year: i32, years: i32| {
+        year.checked_add(years)
+            .and_then(|year| NaiveDate::from_ymd_opt(year, 1, 1))
+    };
+    jan_first(year, -1)?;
+    jan_first(year, 2)?;
+    let start = |jan_first| SystemTime::from(start_of_day(timezone, jan_first));
+
+    Some((start(jan_first(year, 0)?), start(jan_first(year, 1)?)))
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+/// Why a recurrence could not be built from its options
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecurrenceError {
+    /// An interval of zero would never advance
+    ZeroInterval,
+    /// The end leaves out even `dtstart`: `End::Until` is not after it or
+    /// `End::UntilInclusive` is earlier than it
+    UntilBeforeStart,
+    /// No timezone was given and the `local-tz` feature is off, so there is
+    /// no local one to default to
+    MissingTimezone,
+    /// The interval is too large to step by: a step that long is past the
+    /// range of dates chrono can represent
+    IntervalTooLarge,
+    /// A day of the month outside of `1..=31` or `-31..=-1`
+    InvalidMonthDay(i8),
+    /// An hour of the day outside of `0..=23`
+    InvalidHour(u32),
+    /// A minute outside of `0..=59`
+    InvalidMinute(u32),
+    /// A second outside of `0..=59`
+    InvalidSecond(u32),
+    /// A month outside of `1..=12`
+    InvalidMonth(u32),
+    /// A day of the year outside of `1..=366`, either way
+    InvalidYearDay(i16),
+    /// An ISO week of the year outside of `1..=53` or `-53..=-1`
+    InvalidWeekNumber(i8),
+    /// The days, weeks or months picked never line up on a date the rule
+    /// steps on, e.g. the 31st with `by_month: vec![4]`
+    NoMatchingDate,
+    /// A timezone name that is neither an IANA name nor a UTC offset
+    UnknownTimezone(String),
+    /// A date past the range chrono can represent, some 262,000 years either
+    /// way from the epoch
+    DateOutOfRange,
+}
+
+impl fmt::Display for RecurrenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecurrenceError::ZeroInterval => write!(f, "interval must be greater than zero"),
+            RecurrenceError::UntilBeforeStart => write!(f, "until is earlier than dtstart"),
+            RecurrenceError::MissingTimezone => {
+                write!(f, "a timezone is required without the local-tz feature")
+            }
+            RecurrenceError::IntervalTooLarge => write!(f, "interval is too large"),
+            RecurrenceError::InvalidMonthDay(day) => {
+                write!(f, "{} is not a day of the month", day)
+            }
+            RecurrenceError::InvalidHour(hour) => write!(f, "{} is not an hour of the day", hour),
+            RecurrenceError::InvalidMinute(minute) => write!(f, "{} is not a minute", minute),
+            RecurrenceError::InvalidSecond(second) => write!(f, "{} is not a second", second),
+            RecurrenceError::InvalidMonth(month) => write!(f, "{} is not a month", month),
+            RecurrenceError::InvalidYearDay(day) => write!(f, "{} is not a day of the year", day),
+            RecurrenceError::InvalidWeekNumber(week) => {
+                write!(f, "{} is not a week of the year", week)
+            }
+            RecurrenceError::NoMatchingDate => write!(f, "no date matches the rule"),
+            RecurrenceError::UnknownTimezone(name) => write!(
+                f,
+                "unknown timezone: {} (expected an IANA name, e.g. Europe/Paris, or an offset, e.g. +05:30)",
+                name
+            ),
+            RecurrenceError::DateOutOfRange => write!(f, "date is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for RecurrenceError {}
@@ -0,0 +1,943 @@
+//! Reading and writing recurrences as iCalendar ([RFC 5545]) data
+//!
+//! Each `VEVENT` contributes its `RRULE`s, starting at its `DTSTART`, and
+//! its `RDATE`s and `EXDATE`s. An event without an `RRULE` contributes its
+//! `DTSTART` as a single date. Everything ends up in one [`Set`], so an
+//! `EXDATE` leaves out that instant whichever event it comes from.
+//!
+//! Only the parts of `RRULE` this crate can express are supported: `FREQ`,
+//! `INTERVAL`, `COUNT`, `UNTIL` and `WKST` (which is ignored), along with
+//! `BYDAY=MO,TU,WE,TH,FR` without an interval and `BYHOUR`, `BYMINUTE` and
+//! `BYSECOND` for daily rules, `BYMONTHDAY` and `BYMONTH` for monthly ones
+//! and `BYYEARDAY`, `BYWEEKNO` and `BYMONTH` for yearly ones.
+//!
+//! [`Set::to_ical`] writes a `VEVENT` per rule and per rdate. The DST
+//! policies aren't part of the format and are left out. Business-day rules
+//! are written with `BYDAY=MO,TU,WE,TH,FR`, which only picks the same days
+//! when the interval is 1: `INTERVAL` counts calendar days there, so those
+//! with another interval can't be written. Neither can rules with holidays:
+//! an `EXDATE` for each would still count towards `COUNT` and `INTERVAL`,
+//! and leave out the dates of every other rule too. Monthly rules on
+//! specific days are written with `BYMONTHDAY` and yearly rules on days of
+//! the year or ISO weeks with `BYYEARDAY` and `BYWEEKNO`, any of them
+//! limited to some months with `BYMONTH`. Daily rules on several times of
+//! the day are written with `BYHOUR`, `BYMINUTE` and `BYSECOND`.
+//!
+//! [RFC 5545]: https://tools.ietf.org/html/rfc5545
+
+use crate::{
+    daily, dst, monthly, rrule, tz_date_iterator::local_tz, yearly, AmbiguousPolicy, Daily, End,
+    Frequency, Monthly, NonexistentPolicy, RRule, RecurrenceError, Set, TimeZoneSpec, Yearly,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use std::{fmt, io::Read, time::SystemTime};
+
+/// Why iCalendar data could not be read into a [`Set`], or a [`Set`]
+/// written as iCalendar data
+#[derive(Debug)]
+pub enum IcalError {
+    Io(std::io::Error),
+    /// A `VEVENT` without a `DTSTART`
+    MissingDtstart,
+    /// A date or date-time value that could not be parsed
+    InvalidDate(String),
+    /// A `TZID` that is not an IANA timezone name
+    UnknownTimezone(String),
+    /// An `RRULE` that is malformed, e.g. without a `FREQ`
+    InvalidRule(String),
+    /// An `RRULE` or date using parts of the spec this crate doesn't support
+    Unsupported(String),
+    /// The rule was read but its options are invalid
+    Recurrence(RecurrenceError),
+    /// A rule with options that no `RRULE` picks the same dates for
+    Inexpressible(String),
+}
+
+impl fmt::Display for IcalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IcalError::Io(e) => write!(f, "could not read the calendar: {}", e),
+            IcalError::MissingDtstart => write!(f, "event without a DTSTART"),
+            IcalError::InvalidDate(date) => write!(f, "invalid date: {}", date),
+            IcalError::UnknownTimezone(tzid) => write!(f, "unknown timezone: {}", tzid),
+            IcalError::InvalidRule(rule) => write!(f, "invalid RRULE: {}", rule),
+            IcalError::Unsupported(what) => write!(f, "unsupported: {}", what),
+            IcalError::Recurrence(e) => e.fmt(f),
+            IcalError::Inexpressible(what) => write!(f, "not expressible as an RRULE: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for IcalError {}
+
+impl From<std::io::Error> for IcalError {
+    fn from(e: std::io::Error) -> Self {
+        IcalError::Io(e)
+    }
+}
+
+impl From<RecurrenceError> for IcalError {
+    fn from(e: RecurrenceError) -> Self {
+        IcalError::Recurrence(e)
+    }
+}
+
+/// Reads every `VEVENT` in `reader` into a single [`Set`]
+pub fn from_ical(mut reader: impl Read) -> Result<Set, IcalError> {
+    let mut calendar = String::new();
+    reader.read_to_string(&mut calendar)?;
+
+    let mut set = Set::new();
+    let mut event: Option<Event> = None;
+
+    for line in unfold(&calendar) {
+        let line = ContentLine::parse(&line);
+
+        match (line.name.as_str(), &mut event) {
+            ("BEGIN", None) if line.value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some(Event::default());
+            }
+            ("END", Some(_)) if line.value.eq_ignore_ascii_case("VEVENT") => {
+                set = event.take().expect("bug: inside an event").add_to(set)?;
+            }
+            ("DTSTART", Some(event)) => event.dtstart = Some(DateValue::parse(&line)?),
+            ("RRULE", Some(event)) => event.rrules.push(line.value),
+            ("RDATE", Some(event)) => event.rdates.extend(DateValue::parse_list(&line)?),
+            ("EXDATE", Some(event)) => event.exdates.extend(DateValue::parse_list(&line)?),
+            _ => {}
+        }
+    }
+
+    Ok(set)
+}
+
+/// Joins lines folded by starting them with a space or a tab
+fn unfold(calendar: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    for line in calendar.lines() {
+        match (
+            line.strip_prefix(|c| c == ' ' || c == '\t'),
+            lines.last_mut(),
+        ) {
+            (Some(continued), Some(last)) => last.push_str(continued),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+
+    lines
+}
+
+/// `NAME;PARAM=VALUE;...:VALUE`
+struct ContentLine {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl ContentLine {
+    fn parse(line: &str) -> ContentLine {
+        // the value starts at the first colon outside of a quoted parameter
+        let mut quoted = false;
+        let split = line
+            .char_indices()
+            .find(|&(_, c)| {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                c == ':' && !quoted
+            })
+            .map_or(line.len(), |(i, _)| i);
+
+        let (head, value) = line.split_at(split);
+        let mut head = head.split(';');
+        let name = head.next().unwrap_or_default().to_ascii_uppercase();
+        let params = head
+            .filter_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                Some((key.to_ascii_uppercase(), value.trim_matches('"').to_owned()))
+            })
+            .collect();
+
+        ContentLine {
+            name,
+            params,
+            value: value.trim_start_matches(':').to_owned(),
+        }
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A date or date-time as written, before it is placed in a timezone
+#[derive(Clone, Copy)]
+struct DateValue {
+    local: NaiveDateTime,
+    /// `None` for floating times, which are read in the local timezone
+    timezone: Option<TimeZoneSpec>,
+}
+
+impl DateValue {
+    fn parse(line: &ContentLine) -> Result<DateValue, IcalError> {
+        Self::parse_value(&line.value, line.param("TZID"), line.param("VALUE"))
+    }
+
+    fn parse_list(line: &ContentLine) -> Result<Vec<DateValue>, IcalError> {
+        line.value
+            .split(',')
+            .map(|value| Self::parse_value(value, line.param("TZID"), line.param("VALUE")))
+            .collect()
+    }
+
+    fn parse_value(
+        value: &str,
+        tzid: Option<&str>,
+        kind: Option<&str>,
+    ) -> Result<DateValue, IcalError> {
+        let invalid = || IcalError::InvalidDate(value.to_owned());
+
+        let timezone = match tzid {
+            Some(tzid) => Some(TimeZoneSpec::Named(
+                tzid.parse::<Tz>()
+                    .map_err(|_| IcalError::UnknownTimezone(tzid.to_owned()))?,
+            )),
+            None => None,
+        };
+
+        match kind.map(str::to_ascii_uppercase).as_deref() {
+            Some("DATE") => {
+                let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| invalid())?;
+                Ok(DateValue {
+                    local: date.and_time(chrono::NaiveTime::MIN),
+                    timezone,
+                })
+            }
+            None | Some("DATE-TIME") => match value.strip_suffix('Z') {
+                Some(utc) => Ok(DateValue {
+                    local: NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+                        .map_err(|_| invalid())?,
+                    timezone: Some(chrono_tz::UTC.into()),
+                }),
+                None => Ok(DateValue {
+                    local: NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+                        .map_err(|_| invalid())?,
+                    timezone,
+                }),
+            },
+            Some(kind) => Err(IcalError::Unsupported(format!("VALUE={}", kind))),
+        }
+    }
+
+    /// The instant this is at, reading floating times in `fallback`
+    fn instant(self, fallback: Option<TimeZoneSpec>) -> Result<SystemTime, IcalError> {
+        let timezone = self
+            .timezone
+            .or(fallback)
+            .or_else(local_tz)
+            .ok_or(RecurrenceError::MissingTimezone)?;
+
+        match dst::resolve(
+            timezone,
+            self.local,
+            AmbiguousPolicy::First,
+            NonexistentPolicy::ShiftForward,
+        ) {
+            dst::Resolution::At(instant) => Ok(instant.into()),
+            _ => unreachable!("bug: these policies always place the date"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Event {
+    dtstart: Option<DateValue>,
+    rrules: Vec<String>,
+    rdates: Vec<DateValue>,
+    exdates: Vec<DateValue>,
+}
+
+impl Event {
+    fn add_to(self, mut set: Set) -> Result<Set, IcalError> {
+        let dtstart = self.dtstart.ok_or(IcalError::MissingDtstart)?;
+
+        if self.rrules.is_empty() {
+            set = set.rdate(dtstart.instant(None)?);
+        }
+        for rrule in &self.rrules {
+            set = set.rrule(parse_rrule(rrule, dtstart)?);
+        }
+        for rdate in self.rdates {
+            set = set.rdate(rdate.instant(dtstart.timezone)?);
+        }
+        for exdate in self.exdates {
+            set = set.exdate(exdate.instant(dtstart.timezone)?);
+        }
+
+        Ok(set)
+    }
+}
+
+fn parse_rrule(rrule: &str, dtstart: DateValue) -> Result<RRule, IcalError> {
+    let invalid = || IcalError::InvalidRule(rrule.to_owned());
+
+    let mut freq = None;
+    let mut interval = None;
+    let mut end = End::Never;
+    let mut by = ByParts::default();
+
+    for part in rrule.split(';') {
+        let (key, value) = part.split_once('=').ok_or_else(invalid)?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => freq = Some(value.to_ascii_uppercase()),
+            "INTERVAL" => interval = Some(value.parse::<u64>().map_err(|_| invalid())?),
+            // COUNT and UNTIL can't both be set
+            "COUNT" if end == End::Never => {
+                end = End::Count(value.parse().map_err(|_| invalid())?);
+            }
+            "UNTIL" if end == End::Never => {
+                let kind = if value.contains('T') {
+                    None
+                } else {
+                    Some("DATE")
+                };
+                let until = DateValue::parse_value(value, None, kind)?;
+                // an occurrence landing right on UNTIL is still part of the rule
+                end = End::UntilInclusive(until.instant(dtstart.timezone)?);
+            }
+            "COUNT" | "UNTIL" => return Err(invalid()),
+            "WKST" => {}
+            "BYHOUR" => by.hours = by.list("BYHOUR", value).ok_or_else(invalid)?,
+            "BYMINUTE" => by.minutes = by.list("BYMINUTE", value).ok_or_else(invalid)?,
+            "BYSECOND" => by.seconds = by.list("BYSECOND", value).ok_or_else(invalid)?,
+            "BYMONTHDAY" => by.month_days = by.list("BYMONTHDAY", value).ok_or_else(invalid)?,
+            "BYMONTH" => by.months = by.list("BYMONTH", value).ok_or_else(invalid)?,
+            "BYYEARDAY" => by.year_days = by.list("BYYEARDAY", value).ok_or_else(invalid)?,
+            "BYWEEKNO" => by.week_numbers = by.list("BYWEEKNO", value).ok_or_else(invalid)?,
+            "BYDAY" => by.weekdays = by.list("BYDAY", value).ok_or_else(invalid)?,
+            other => return Err(IcalError::Unsupported(other.to_owned())),
+        }
+    }
+
+    let dtstart_instant = Some(dtstart.instant(None)?);
+    let timezone = dtstart.timezone;
+
+    let frequency = match freq.as_deref().ok_or_else(invalid)? {
+        "SECONDLY" => Frequency::Secondly,
+        "MINUTELY" => Frequency::Minutely,
+        "HOURLY" => Frequency::Hourly,
+        "DAILY" => Frequency::Daily,
+        "WEEKLY" => Frequency::Weekly,
+        "MONTHLY" => Frequency::Monthly,
+        "YEARLY" => Frequency::Yearly,
+        _ => return Err(invalid()),
+    };
+
+    // only the parts a frequency's own options can express
+    let expressible: &[&str] = match frequency {
+        Frequency::Daily => &["BYDAY", "BYHOUR", "BYMINUTE", "BYSECOND"],
+        Frequency::Monthly => &["BYMONTHDAY", "BYMONTH"],
+        Frequency::Yearly => &["BYYEARDAY", "BYWEEKNO", "BYMONTH"],
+        _ => &[],
+    };
+    if let Some(part) = by.given.iter().find(|part| !expressible.contains(part)) {
+        return Err(IcalError::Unsupported((*part).to_owned()));
+    }
+    // only business days, which the interval can't count as calendar days
+    let skip_weekends = !by.weekdays.is_empty();
+    if skip_weekends {
+        let mut weekdays: Vec<_> = by
+            .weekdays
+            .iter()
+            .map(|day| day.to_ascii_uppercase())
+            .collect();
+        weekdays.sort_unstable();
+        weekdays.dedup();
+        if weekdays != ["FR", "MO", "TH", "TU", "WE"] || interval.unwrap_or(1) != 1 {
+            return Err(IcalError::Unsupported("BYDAY".to_owned()));
+        }
+    }
+
+    let rule = match frequency {
+        _ if by.given.is_empty() => RRule::try_new(
+            frequency,
+            rrule::Options {
+                interval,
+                dtstart: dtstart_instant,
+                timezone,
+                end,
+                ..rrule::Options::default()
+            },
+        )?,
+        Frequency::Daily => RRule::Daily(Daily::try_new(daily::Options {
+            interval,
+            dtstart: dtstart_instant,
+            timezone,
+            end,
+            skip_weekends,
+            by_hour: by.hours,
+            by_minute: by.minutes,
+            by_second: by.seconds,
+            ..daily::Options::default()
+        })?),
+        Frequency::Monthly => RRule::Monthly(Monthly::try_new(monthly::Options {
+            interval,
+            dtstart: dtstart_instant,
+            timezone,
+            end,
+            by_month_day: by.month_days,
+            by_month: by.months,
+            ..monthly::Options::default()
+        })?),
+        Frequency::Yearly => RRule::Yearly(Yearly::try_new(yearly::Options {
+            interval,
+            dtstart: dtstart_instant,
+            timezone,
+            end,
+            by_year_day: by.year_days,
+            by_week_no: by.week_numbers,
+            by_month: by.months,
+            ..yearly::Options::default()
+        })?),
+        _ => unreachable!("bug: other frequencies can't express any BY part"),
+    };
+
+    Ok(rule)
+}
+
+/// The `BY*` parts of an `RRULE` that some frequency can express
+#[derive(Default)]
+struct ByParts {
+    hours: Vec<u32>,
+    minutes: Vec<u32>,
+    seconds: Vec<u32>,
+    month_days: Vec<i8>,
+    months: Vec<u32>,
+    year_days: Vec<i16>,
+    week_numbers: Vec<i8>,
+    weekdays: Vec<String>,
+    /// Which parts were given, to refuse those the frequency can't express
+    given: Vec<&'static str>,
+}
+
+impl ByParts {
+    /// The comma-separated values of the `key` part; `None` if any of them
+    /// is malformed
+    fn list<T: std::str::FromStr>(&mut self, key: &'static str, value: &str) -> Option<Vec<T>> {
+        self.given.push(key);
+        value.split(',').map(|v| v.parse().ok()).collect()
+    }
+}
+
+impl Set {
+    /// Writes the set as a `VCALENDAR`, see the [module docs](crate::ical)
+    /// for the rules that can't be written
+    pub fn to_ical(&self) -> Result<String, IcalError> {
+        let stamp = format!("DTSTAMP:{}", utc_value(SystemTime::now()));
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_owned(),
+            "VERSION:2.0".to_owned(),
+            "PRODID:-//recurrence//recurrence//EN".to_owned(),
+        ];
+
+        let rules = self
+            .rules()
+            .iter()
+            .map(|rule| {
+                let (dtstart, timezone, rrule) = rule_parts(rule)?;
+                Ok((dtstart_line(dtstart, timezone), Some(rrule)))
+            })
+            .collect::<Result<Vec<_>, IcalError>>()?;
+        let rdates = self
+            .rdates()
+            .iter()
+            .map(|&rdate| (format!("DTSTART:{}", utc_value(rdate)), None));
+
+        for (i, (dtstart, rrule)) in rules.into_iter().chain(rdates).enumerate() {
+            lines.push("BEGIN:VEVENT".to_owned());
+            lines.push(format!("UID:{}@recurrence", i));
+            lines.push(stamp.clone());
+            lines.push(dtstart);
+            lines.extend(rrule.map(|rrule| format!("RRULE:{}", rrule)));
+            // exdates apply to the whole set, so once is enough
+            if i == 0 && !self.exdates().is_empty() {
+                let exdates: Vec<_> = self.exdates().iter().map(|&d| utc_value(d)).collect();
+                lines.push(format!("EXDATE:{}", exdates.join(",")));
+            }
+            lines.push("END:VEVENT".to_owned());
+        }
+
+        lines.push("END:VCALENDAR".to_owned());
+
+        Ok(lines.iter().map(|line| fold(line)).collect())
+    }
+}
+
+/// The `FREQ` and `INTERVAL` stepping by `interval`
+fn every(interval: std::time::Duration) -> (&'static str, u64) {
+    // there are no sub-second frequencies
+    let secs = interval.as_secs().max(1);
+    match secs {
+        _ if secs % (60 * 60) == 0 => ("HOURLY", secs / (60 * 60)),
+        _ if secs % 60 == 0 => ("MINUTELY", secs / 60),
+        _ => ("SECONDLY", secs),
+    }
+}
+
+/// The dtstart, timezone and `RRULE` value of a rule
+fn rule_parts(rule: &RRule) -> Result<(SystemTime, TimeZoneSpec, String), IcalError> {
+    let (freq, interval, dtstart, timezone, end) = match rule {
+        RRule::Custom(c) => {
+            let options = c.to_options();
+            let (freq, interval) = every(options.interval);
+            let (dtstart, timezone) = (options.dtstart, options.timezone);
+            (freq, interval, dtstart, timezone, options.end)
+        }
+        RRule::Daily(d) => {
+            let o = d.to_options();
+            let (freq, interval) = match o.interval_duration {
+                Some(interval) => every(interval),
+                None => ("DAILY", o.interval.unwrap_or(1)),
+            };
+            (freq, interval, o.dtstart, o.timezone, o.end)
+        }
+        RRule::Weekly(w) => {
+            let o = w.to_options();
+            (
+                "WEEKLY",
+                o.interval.unwrap_or(1),
+                o.dtstart,
+                o.timezone,
+                o.end,
+            )
+        }
+        RRule::Monthly(m) => {
+            let o = m.to_options();
+            (
+                "MONTHLY",
+                o.interval.unwrap_or(1),
+                o.dtstart,
+                o.timezone,
+                o.end,
+            )
+        }
+        RRule::Yearly(y) => {
+            let o = y.to_options();
+            (
+                "YEARLY",
+                o.interval.unwrap_or(1),
+                o.dtstart,
+                o.timezone,
+                o.end,
+            )
+        }
+    };
+
+    let mut rrule = format!("FREQ={}", freq);
+    if interval != 1 {
+        rrule.push_str(&format!(";INTERVAL={}", interval));
+    }
+    if let RRule::Daily(d) = rule {
+        let o = d.to_options();
+        if !o.holidays.is_empty() {
+            return Err(IcalError::Inexpressible("holidays".to_owned()));
+        }
+        if o.skip_weekends {
+            // INTERVAL would count calendar days rather than business days
+            if interval != 1 {
+                return Err(IcalError::Inexpressible(
+                    "business days with an interval".to_owned(),
+                ));
+            }
+            rrule.push_str(";BYDAY=MO,TU,WE,TH,FR");
+        }
+        if !o.all_day && o.interval_duration.is_none() {
+            for (key, listed) in [
+                ("BYHOUR", &o.by_hour),
+                ("BYMINUTE", &o.by_minute),
+                ("BYSECOND", &o.by_second),
+            ] {
+                if !listed.is_empty() {
+                    let listed: Vec<_> = listed.iter().map(ToString::to_string).collect();
+                    rrule.push_str(&format!(";{}={}", key, listed.join(",")));
+                }
+            }
+        }
+    }
+    if let RRule::Monthly(m) = rule {
+        let days = m.to_options().by_month_day;
+        if !days.is_empty() {
+            let days: Vec<_> = days.iter().map(ToString::to_string).collect();
+            rrule.push_str(&format!(";BYMONTHDAY={}", days.join(",")));
+        }
+        push_by_month(&mut rrule, &m.to_options().by_month);
+    }
+    if let RRule::Yearly(y) = rule {
+        let o = y.to_options();
+        if !o.by_year_day.is_empty() {
+            let days: Vec<_> = o.by_year_day.iter().map(ToString::to_string).collect();
+            rrule.push_str(&format!(";BYYEARDAY={}", days.join(",")));
+        }
+        if !o.by_week_no.is_empty() {
+            let weeks: Vec<_> = o.by_week_no.iter().map(ToString::to_string).collect();
+            rrule.push_str(&format!(";BYWEEKNO={}", weeks.join(",")));
+        }
+        push_by_month(&mut rrule, &o.by_month);
+    }
+
+    let until = match end {
+        End::Never => None,
+        End::Count(count) => {
+            rrule.push_str(&format!(";COUNT={}", count));
+            None
+        }
+        End::UntilInclusive(until) => Some(until),
+        // UNTIL is inclusive and can't be combined with COUNT, so stop right
+        // at the last occurrence instead
+        End::Until(_) | End::CountOrUntil { .. } | End::AfterDuration(_) => {
+            match rule.all().last() {
+                Some(last) => Some(last),
+                None => {
+                    rrule.push_str(";COUNT=0");
+                    None
+                }
+            }
+        }
+    };
+    if let Some(until) = until {
+        rrule.push_str(&format!(";UNTIL={}", utc_value(until)));
+    }
+
+    let dtstart = dtstart.expect("bug: rules always have a dtstart");
+    let timezone = timezone.expect("bug: rules always have a timezone");
+
+    Ok((dtstart, timezone, rrule))
+}
+
+/// Appends `BYMONTH` when a rule is limited to some months
+fn push_by_month(rrule: &mut String, months: &[u32]) {
+    if !months.is_empty() {
+        let months: Vec<_> = months.iter().map(ToString::to_string).collect();
+        rrule.push_str(&format!(";BYMONTH={}", months.join(",")));
+    }
+}
+
+/// `DTSTART` with a `TZID` for named timezones, in UTC otherwise; a fixed
+/// offset never changes so UTC steps the same way
+fn dtstart_line(dtstart: SystemTime, timezone: TimeZoneSpec) -> String {
+    match timezone {
+        TimeZoneSpec::Named(tz) if tz != chrono_tz::UTC => {
+            let local = DateTime::<Utc>::from(dtstart).with_timezone(&tz);
+            format!(
+                "DTSTART;TZID={}:{}",
+                tz.name(),
+                local.format("%Y%m%dT%H%M%S")
+            )
+        }
+        _ => format!("DTSTART:{}", utc_value(dtstart)),
+    }
+}
+
+fn utc_value(date: SystemTime) -> String {
+    DateTime::<Utc>::from(date)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Splits `line` so no line is longer than 75 octets, continuing each one
+/// with a space
+fn fold(line: &str) -> String {
+    const MAX_OCTETS: usize = 75;
+
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut octets = 0;
+
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use chrono::TimeZone as _;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//recurrence//tests//EN\r
+BEGIN:VEVENT\r
+UID:standup\r
+DTSTART;TZID=America/New_York:20191101T090000\r
+RRULE:FREQ=DAILY;COUNT=5\r
+EXDATE;TZID=America/New_York:20191102T090000,\r
+ 20191104T090000\r
+SUMMARY:Stand-up meeting with a description long enough that it gets fol\r
+ ded\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:one-off\r
+DTSTART:20191103T200000Z\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:review\r
+DTSTART:20191101T150000Z\r
+RRULE:FREQ=WEEKLY;INTERVAL=2;UNTIL=20191115T150000Z\r
+RDATE:20191108T150000Z\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn from_ical() {
+        let set = super::from_ical(CALENDAR.as_bytes()).unwrap();
+        let eastern = |d, h| {
+            SystemTime::from(
+                chrono_tz::America::New_York
+                    .with_ymd_and_hms(2019, 11, d, h, 0, 0)
+                    .unwrap(),
+            )
+        };
+        let utc = |d, h| {
+            SystemTime::from(
+                chrono_tz::UTC
+                    .with_ymd_and_hms(2019, 11, d, h, 0, 0)
+                    .unwrap(),
+            )
+        };
+
+        assert_eq!(
+            vec![
+                eastern(1, 9),
+                utc(1, 15),
+                eastern(3, 9),
+                utc(3, 20),
+                eastern(5, 9),
+                utc(8, 15),
+                utc(15, 15),
+            ],
+            set.all().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn to_ical_round_trip() {
+        let set = super::from_ical(CALENDAR.as_bytes()).unwrap();
+        let ical = set.to_ical().unwrap();
+        let parsed = super::from_ical(ical.as_bytes()).unwrap();
+
+        assert!(ical.contains("DTSTART;TZID=America/New_York:20191101T090000\r\n"));
+        assert!(ical.contains("RRULE:FREQ=DAILY;COUNT=5\r\n"));
+        assert!(set.all().eq(parsed.all()));
+    }
+
+    #[test]
+    fn to_ical_ends() {
+        let dates = |end| {
+            let set = Set::new().rrule(RRule::Daily(Daily::utc(july_first(), 2, end)));
+            let parsed = super::from_ical(set.to_ical().unwrap().as_bytes()).unwrap();
+            assert!(set.all().take(20).eq(parsed.all().take(20)));
+        };
+
+        dates(End::Never);
+        dates(End::Count(3));
+        dates(End::Until(july_first() + 4 * ONE_DAY));
+        dates(End::UntilInclusive(july_first() + 4 * ONE_DAY));
+        dates(End::CountOrUntil {
+            count: 10,
+            until: july_first() + 5 * ONE_DAY,
+        });
+        dates(End::CountOrUntil {
+            count: 0,
+            until: july_first() + 4 * ONE_DAY,
+        });
+    }
+
+    // semi_monthly only ever uses the local timezone
+    #[cfg(feature = "local-tz")]
+    #[test]
+    fn to_ical_semi_monthly_round_trip() {
+        let semi_monthly = Monthly::semi_monthly(july_first(), End::Count(6));
+        let set = Set::new().rrule(RRule::Monthly(semi_monthly));
+        let parsed = super::from_ical(set.to_ical().unwrap().as_bytes()).unwrap();
+
+        assert!(set.all().eq(parsed.all()));
+    }
+
+    #[test]
+    fn to_ical_by_parts_round_trip() {
+        let rules = [
+            RRule::Monthly(Monthly::new(monthly::Options {
+                dtstart: Some(july_first()),
+                timezone: Some(chrono_tz::America::New_York.into()),
+                by_month_day: vec![-1],
+                by_month: vec![2, 8],
+                end: End::Count(4),
+                ..monthly::Options::default()
+            })),
+            RRule::Yearly(Yearly::new(yearly::Options {
+                dtstart: Some(july_first()),
+                timezone: Some(chrono_tz::UTC.into()),
+                by_year_day: vec![1, 100],
+                end: End::Count(4),
+                ..yearly::Options::default()
+            })),
+            RRule::Yearly(Yearly::new(yearly::Options {
+                dtstart: Some(july_first()),
+                timezone: Some(chrono_tz::UTC.into()),
+                interval: Some(2),
+                by_week_no: vec![1, -1],
+                by_month: vec![1],
+                end: End::Count(20),
+                ..yearly::Options::default()
+            })),
+            RRule::Daily(Daily::new(daily::Options {
+                dtstart: Some(july_first()),
+                timezone: Some(chrono_tz::Europe::Paris.into()),
+                by_hour: vec![9, 17],
+                by_minute: vec![0, 30],
+                end: End::Count(8),
+                ..daily::Options::default()
+            })),
+        ];
+
+        for rule in rules {
+            let set = Set::new().rrule(rule);
+            let parsed = super::from_ical(set.to_ical().unwrap().as_bytes()).unwrap();
+            assert!(set.all().eq(parsed.all()));
+        }
+    }
+
+    #[test]
+    fn to_ical_business_days() {
+        let business_days = |interval| {
+            let daily = Daily::builder()
+                .dtstart(july_first())
+                .timezone(chrono_tz::America::New_York)
+                .skip_weekends()
+                .interval(interval)
+                .count(10)
+                .build()
+                .unwrap();
+            Set::new().rrule(RRule::Daily(daily))
+        };
+
+        let set = business_days(1);
+        let ical = set.to_ical().unwrap();
+        let parsed = super::from_ical(ical.as_bytes()).unwrap();
+        assert!(ical.contains(";BYDAY=MO,TU,WE,TH,FR"));
+        assert!(set.all().eq(parsed.all()));
+
+        // INTERVAL=2 would be every other calendar day, weekends left out
+        let every_other = business_days(2).to_ical();
+        assert!(matches!(every_other, Err(IcalError::Inexpressible(_))));
+
+        let holiday = NaiveDate::from_ymd_opt(2020, 7, 3).unwrap();
+        let holidays = Daily::builder()
+            .dtstart(july_first())
+            .timezone(chrono_tz::America::New_York)
+            .holidays([holiday])
+            .build()
+            .unwrap();
+        let holidays = Set::new().rrule(RRule::Daily(holidays)).to_ical();
+        assert!(matches!(holidays, Err(IcalError::Inexpressible(what)) if what == "holidays"));
+    }
+
+    #[test]
+    fn fold() {
+        let line = format!("SUMMARY:{}", "é".repeat(50));
+        let folded = super::fold(&line);
+
+        assert!(folded.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(vec![line], super::unfold(&folded));
+    }
+
+    #[test]
+    fn unfold() {
+        let lines = super::unfold("A:1\r\n 2\r\n\t3\r\nB:4\r\n");
+        assert_eq!(vec!["A:123", "B:4"], lines);
+    }
+
+    #[test]
+    fn errors() {
+        let event = |body: &str| {
+            format!(
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\n{}\nEND:VEVENT\nEND:VCALENDAR\n",
+                body
+            )
+        };
+
+        let missing = super::from_ical(event("RRULE:FREQ=DAILY").as_bytes());
+        assert!(matches!(missing, Err(IcalError::MissingDtstart)));
+
+        let tz = super::from_ical(event("DTSTART;TZID=Mars/Phobos:20191101T090000").as_bytes());
+        assert!(matches!(tz, Err(IcalError::UnknownTimezone(name)) if name == "Mars/Phobos"));
+
+        for dtstart in ["DTSTART:20190230T090000Z", "DTSTART;VALUE=DATE:20190230"] {
+            let feb_30 = super::from_ical(event(dtstart).as_bytes());
+            assert!(
+                matches!(feb_30, Err(IcalError::InvalidDate(date)) if date.starts_with("20190230"))
+            );
+        }
+
+        let byday = super::from_ical(
+            event("DTSTART:20191101T090000Z\nRRULE:FREQ=WEEKLY;BYDAY=MO,WE").as_bytes(),
+        );
+        assert!(matches!(byday, Err(IcalError::Unsupported(part)) if part == "BYDAY"));
+
+        for rrule in [
+            "FREQ=DAILY;BYDAY=MO,WE",
+            "FREQ=DAILY;INTERVAL=2;BYDAY=MO,TU,WE,TH,FR",
+        ] {
+            let byday = super::from_ical(
+                event(&format!("DTSTART:20191101T090000Z\nRRULE:{}", rrule)).as_bytes(),
+            );
+            assert!(matches!(byday, Err(IcalError::Unsupported(part)) if part == "BYDAY"));
+        }
+
+        let weekly_months = super::from_ical(
+            event("DTSTART:20191101T090000Z\nRRULE:FREQ=WEEKLY;BYMONTH=1").as_bytes(),
+        );
+        assert!(matches!(weekly_months, Err(IcalError::Unsupported(part)) if part == "BYMONTH"));
+
+        let malformed = super::from_ical(
+            event("DTSTART:20191101T090000Z\nRRULE:FREQ=MONTHLY;BYMONTHDAY=1,x").as_bytes(),
+        );
+        assert!(matches!(malformed, Err(IcalError::InvalidRule(_))));
+
+        let zero = super::from_ical(
+            event("DTSTART:20191101T090000Z\nRRULE:FREQ=DAILY;INTERVAL=0").as_bytes(),
+        );
+        assert!(matches!(
+            zero,
+            Err(IcalError::Recurrence(RecurrenceError::ZeroInterval))
+        ));
+    }
+
+    #[test]
+    fn hourly() {
+        let set = super::from_ical(
+            "BEGIN:VEVENT\nDTSTART:20200701T040445Z\nRRULE:FREQ=HOURLY;INTERVAL=3;COUNT=2\nEND:VEVENT\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![july_first(), july_first() + 3 * ONE_HOUR],
+            set.all().collect::<Vec<_>>()
+        );
+    }
+}
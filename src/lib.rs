@@ -1,30 +1,131 @@
+pub mod custom;
 pub mod daily;
+pub mod ical;
+pub mod monthly;
+pub mod rrule;
 pub mod weekly;
+pub mod yearly;
 
-mod rrule;
+mod adapters;
+mod csv;
+mod describe;
+mod dst;
+mod error;
+mod recurrence;
+#[cfg(feature = "serde")]
+mod rfc3339;
 mod set;
+#[cfg(feature = "time")]
+mod time_interop;
+mod timezone;
+#[cfg(feature = "toml")]
+mod toml_config;
 mod tz_date_iterator;
 
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
-pub use crate::{daily::Daily, rrule::RRule, set::Set, weekly::Weekly};
+pub use crate::{
+    adapters::{DatesExt, Gaps, Pairs, SkipUntil, TakeUntil},
+    custom::Custom,
+    daily::Daily,
+    dst::{AmbiguousPolicy, NonexistentPolicy},
+    error::RecurrenceError,
+    monthly::Monthly,
+    recurrence::{BoxedDates, Recurrence},
+    rrule::{Frequency, RRule, RecurrenceIter},
+    set::{Set, SetBuffer, COINCIDENCE_SEARCH_LIMIT},
+    timezone::{timezone_names, SpecOffset, TimeZoneSpec},
+    tz_date_iterator::{BoundedIterator, CountedIterator},
+    weekly::Weekly,
+    yearly::Yearly,
+};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum End {
-    Until(SystemTime),
+    /// Stops before this instant: an occurrence landing exactly on it is left out
+    Until(#[cfg_attr(feature = "serde", serde(with = "crate::rfc3339"))] SystemTime),
+    /// Stops after this instant: an occurrence landing exactly on it is kept
+    UntilInclusive(#[cfg_attr(feature = "serde", serde(with = "crate::rfc3339"))] SystemTime),
     Count(usize),
+    /// Stops at whichever comes first: `count` occurrences or `until`
+    /// (exclusive, like `Until`)
+    CountOrUntil {
+        count: usize,
+        #[cfg_attr(feature = "serde", serde(with = "crate::rfc3339"))]
+        until: SystemTime,
+    },
+    /// Stops this long after dtstart, exclusive like `Until`. Rules turn it
+    /// into that `Until` when they are built.
+    AfterDuration(Duration),
+    #[default]
     Never,
 }
 
-impl Default for End {
-    fn default() -> Self {
-        End::Never
+impl End {
+    /// The end as an instant rather than relative to `dtstart`
+    ///
+    /// An until that leaves out dtstart itself can only be a mistake: a
+    /// series that is meant to be empty says so with `End::Count(0)`
+    fn resolve(self, dtstart: SystemTime) -> Result<End, RecurrenceError> {
+        let end = match self {
+            End::AfterDuration(duration) => End::Until(
+                dtstart
+                    .checked_add(duration)
+                    .ok_or(RecurrenceError::DateOutOfRange)?,
+            ),
+            end => end,
+        };
+
+        match end {
+            End::Until(until) | End::UntilInclusive(until) | End::CountOrUntil { until, .. }
+                if tz_date_iterator::from_system_to_naive(until).is_err() =>
+            {
+                Err(RecurrenceError::DateOutOfRange)
+            }
+            End::Until(until) | End::CountOrUntil { until, .. } if until <= dtstart => {
+                Err(RecurrenceError::UntilBeforeStart)
+            }
+            End::UntilInclusive(until) if until < dtstart => Err(RecurrenceError::UntilBeforeStart),
+            end => Ok(end),
+        }
+    }
+
+    /// Limits the occurrences to `count`, keeping any until already set
+    fn with_count(self, count: usize) -> End {
+        match self {
+            End::Until(until) | End::CountOrUntil { until, .. } => {
+                End::CountOrUntil { count, until }
+            }
+            _ => End::Count(count),
+        }
+    }
+
+    /// Stops before `until`, keeping any count already set
+    fn with_until(self, until: SystemTime) -> End {
+        match self {
+            End::Count(count) | End::CountOrUntil { count, .. } => {
+                End::CountOrUntil { count, until }
+            }
+            _ => End::Until(until),
+        }
+    }
+
+    /// Takes occurrences that were skipped over out of the count, if any
+    fn skip(&mut self, skipped: usize) {
+        if let End::Count(count) | End::CountOrUntil { count, .. } = self {
+            *count = count.saturating_sub(skipped);
+        }
     }
 }
 
 #[cfg(test)]
 pub mod test_helpers {
-    use std::time::{SystemTime, Duration};
+    use std::time::{Duration, SystemTime};
 
     pub const ONE_MINUTE: Duration = Duration::from_secs(60);
     pub const ONE_HOUR: Duration = Duration::from_secs(60 * ONE_MINUTE.as_secs());
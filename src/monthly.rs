@@ -0,0 +1,586 @@
+use crate::{
+    dst::{self, Resolution},
+    tz_date_iterator::{
+        from_naive_to_system, from_system_to_naive, local_tz, matching_horizon, month_index,
+        nth_month, BoundedIterator, Step, TzDateIterator,
+    },
+    AmbiguousPolicy, End, NonexistentPolicy, RecurrenceError, RecurrenceIter, TimeZoneSpec,
+};
+use chrono::{DateTime, Datelike as _, Months, NaiveDate, NaiveDateTime, TimeZone as _};
+use chrono_tz::Tz;
+use std::{convert::TryFrom, sync::Arc, time::SystemTime};
+
+/// Recurs on dtstart's day of the month, or on the days in `by_month_day`,
+/// every `interval` months, keeping only the months in `by_month` if any.
+/// Months too short to have that day (e.g. the 31st in April) are left out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Monthly {
+    interval: u64,
+    by_month_day: Vec<i8>,
+    by_month: Vec<u32>,
+    timezone: TimeZoneSpec,
+    dtstart: NaiveDateTime,
+    end: End,
+    dst_ambiguous: AmbiguousPolicy,
+    dst_nonexistent: NonexistentPolicy,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Options {
+    pub interval: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::rfc3339::option"))]
+    pub dtstart: Option<SystemTime>,
+    pub timezone: Option<TimeZoneSpec>,
+    pub end: End,
+    pub dst_ambiguous: AmbiguousPolicy,
+    pub dst_nonexistent: NonexistentPolicy,
+    /// Lands on each of these days of the month instead of dtstart's, at
+    /// dtstart's time. Negative days count back from the end of the month,
+    /// e.g. -1 is the last day. A dtstart on another day moves to the first
+    /// one that matches; when none ever does, e.g. the 30th every 12 months
+    /// from February, `try_new` fails with `NoMatchingDate`.
+    pub by_month_day: Vec<i8>,
+    /// Keeps only the stepped months that are one of these, 1 for January
+    /// to 12 for December. A dtstart in another month moves to the first
+    /// one that matches; when none ever does, e.g. the 31st in April only,
+    /// `try_new` fails with `NoMatchingDate`.
+    pub by_month: Vec<u32>,
+}
+
+impl Monthly {
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Monthly::try_new`]
+    pub fn new(options: Options) -> Self {
+        Self::try_new(options).expect("invalid monthly options")
+    }
+
+    /// A recurrence computed in UTC, without looking up the local timezone
+    ///
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Monthly::try_new`]
+    pub fn utc(dtstart: SystemTime, interval: u64, end: End) -> Self {
+        Self::new(Options {
+            dtstart: Some(dtstart),
+            interval: Some(interval),
+            timezone: Some(chrono_tz::UTC.into()),
+            end,
+            ..Options::default()
+        })
+    }
+
+    /// Every three months in the local timezone. Like any monthly rule it
+    /// leaves out the months too short to have dtstart's day.
+    ///
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Monthly::try_new`]
+    pub fn quarterly(dtstart: SystemTime, end: End) -> Self {
+        Self::new(Options {
+            dtstart: Some(dtstart),
+            interval: Some(3),
+            end,
+            ..Options::default()
+        })
+    }
+
+    /// On the 1st and the 15th of every month in the local timezone, e.g.
+    /// for payroll
+    ///
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Monthly::try_new`]
+    pub fn semi_monthly(dtstart: SystemTime, end: End) -> Self {
+        Self::new(Options {
+            dtstart: Some(dtstart),
+            end,
+            by_month_day: vec![1, 15],
+            ..Options::default()
+        })
+    }
+
+    pub fn try_new(options: Options) -> Result<Self, RecurrenceError> {
+        let dtstart = options.dtstart.unwrap_or_else(SystemTime::now);
+        let interval = options.interval.unwrap_or(1);
+
+        if interval == 0 {
+            return Err(RecurrenceError::ZeroInterval);
+        }
+        // months are counted as an i64 when stepping
+        i64::try_from(interval).map_err(|_| RecurrenceError::IntervalTooLarge)?;
+        if let Some(&day) = options
+            .by_month_day
+            .iter()
+            .find(|day| !(1..=31).contains(&day.abs()))
+        {
+            return Err(RecurrenceError::InvalidMonthDay(day));
+        }
+        if let Some(&month) = options
+            .by_month
+            .iter()
+            .find(|month| !(1..=12).contains(*month))
+        {
+            return Err(RecurrenceError::InvalidMonth(month));
+        }
+        let end = options.end.resolve(dtstart)?;
+
+        let timezone = options
+            .timezone
+            .or_else(local_tz)
+            .ok_or(RecurrenceError::MissingTimezone)?;
+        let mut dtstart = from_system_to_naive(dtstart)?;
+        if let Some(matches) = matching_days(
+            timezone.from_utc_datetime(&dtstart).date_naive(),
+            interval,
+            &options.by_month_day,
+            &options.by_month,
+        ) {
+            let within = matching_horizon(interval);
+            dtstart = dst::first_day(timezone, dtstart, matches, within)?;
+        }
+
+        Ok(Monthly {
+            dtstart,
+            timezone,
+            interval,
+            by_month_day: options.by_month_day,
+            by_month: options.by_month,
+            end,
+            dst_ambiguous: options.dst_ambiguous,
+            dst_nonexistent: options.dst_nonexistent,
+        })
+    }
+
+    /// The options that build this same rule back
+    pub fn to_options(&self) -> Options {
+        Options {
+            interval: Some(self.interval),
+            dtstart: Some(from_naive_to_system(self.dtstart)),
+            timezone: Some(self.timezone),
+            end: self.end,
+            dst_ambiguous: self.dst_ambiguous,
+            dst_nonexistent: self.dst_nonexistent,
+            by_month_day: self.by_month_day.clone(),
+            by_month: self.by_month.clone(),
+        }
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = SystemTime> {
+        self.all_local().map(SystemTime::from)
+    }
+
+    /// Like [`Monthly::all`] but also walkable from the back, only for rules
+    /// whose end is bounded
+    pub fn all_bounded(&self) -> Option<BoundedIterator> {
+        self.dates().bounded()
+    }
+
+    /// Like [`Monthly::all`] but keeping each occurrence in the rule's
+    /// timezone, ready to be formatted as a wall-clock time
+    pub fn all_local(&self) -> impl Iterator<Item = DateTime<TimeZoneSpec>> {
+        self.dates()
+    }
+
+    /// Every occurrence from dtstart on
+    pub(crate) fn dates(&self) -> TzDateIterator {
+        let dtstart = self.timezone.from_utc_datetime(&self.dtstart);
+        let matches = matching_days(
+            dtstart.date_naive(),
+            self.interval,
+            &self.by_month_day,
+            &self.by_month,
+        );
+        let step = match matches {
+            Some(matches) => Step::Matching(Arc::new(matches), matching_horizon(self.interval)),
+            None => Step::Months(self.interval),
+        };
+
+        TzDateIterator::new(dtstart, step, self.end)
+            .ambiguous(self.dst_ambiguous)
+            .nonexistent(self.dst_nonexistent)
+    }
+
+    /// Exports up to `max` occurrences as CSV rows of
+    /// `index,iso8601_local,unix_seconds`, rendering local times in `tz`
+    pub fn all_csv(&self, tz: Tz, max: usize) -> String {
+        crate::csv::to_csv(self.all(), tz, max)
+    }
+
+    pub fn after(&self, min: SystemTime) -> impl Iterator<Item = SystemTime> {
+        self.resume(min).1.map(SystemTime::from)
+    }
+
+    /// Each occurrence along with its index in the series
+    pub fn enumerate(&self) -> impl Iterator<Item = (usize, SystemTime)> {
+        self.all().enumerate()
+    }
+
+    /// Like [`Monthly::after`] but each occurrence comes with its index in
+    /// the whole series, counting from dtstart rather than from `min`
+    pub fn enumerate_after(&self, min: SystemTime) -> impl Iterator<Item = (usize, SystemTime)> {
+        let (skipped, dates) = self.resume(min);
+        dates
+            .enumerate()
+            .map(move |(i, date)| (skipped + i, date.into()))
+    }
+
+    /// The occurrences at or after `min`, along with how many were skipped
+    pub(crate) fn resume(&self, min: SystemTime) -> (usize, TzDateIterator) {
+        if !self.by_month_day.is_empty() || !self.by_month.is_empty() {
+            return self.dates().skip_before(min);
+        }
+
+        let dtstart = self.timezone.from_utc_datetime(&self.dtstart);
+
+        TzDateIterator::new(dtstart, Step::Months(self.interval), self.end)
+            .ambiguous(self.dst_ambiguous)
+            .nonexistent(self.dst_nonexistent)
+            .resume(min, |min| {
+                let start = dtstart.naive_local();
+                let interval = self.interval as i64;
+                let months = month_index(min.date_naive()) - month_index(dtstart.date_naive());
+
+                // every step before min's month is skipped, as long as it has the day
+                let mut steps = months / interval;
+                let mut skipped = (0..steps)
+                    .filter(|step| matches!(nth_month(start, step * interval), Ok(Some(_))))
+                    .count();
+
+                loop {
+                    if let Some(cursor) = nth_month(start, steps.checked_mul(interval)?).ok()? {
+                        let resolved = dst::resolve(
+                            self.timezone,
+                            cursor,
+                            self.dst_ambiguous,
+                            self.dst_nonexistent,
+                        );
+                        if !matches!(resolved, Resolution::At(c) if c < min) {
+                            break Some((skipped, cursor));
+                        }
+                        skipped += 1;
+                    }
+                    steps += 1;
+                }
+            })
+    }
+
+    /// The first occurrence at or after `min`
+    pub fn first_after(&self, min: SystemTime) -> Option<SystemTime> {
+        self.after(min).next()
+    }
+}
+
+/// Whether a date is one the rule lands on, for rules that pick days
+/// rather than just stepping by months: on `days`, or else `start`'s day,
+/// and in `months` if any
+fn matching_days(
+    start: NaiveDate,
+    interval: u64,
+    days: &[i8],
+    months: &[u32],
+) -> Option<impl Fn(NaiveDate) -> bool> {
+    if days.is_empty() && months.is_empty() {
+        return None;
+    }
+
+    let days = match days {
+        [] => vec![start.day() as i8],
+        days => days.to_vec(),
+    };
+    let on_day = month_days(start, interval, days);
+    let months = months.to_vec();
+
+    Some(move |date: NaiveDate| {
+        (months.is_empty() || months.contains(&date.month())) && on_day(date)
+    })
+}
+
+/// Whether a date is on one of `days` in a month `interval` months on from
+/// `start`'s
+fn month_days(start: NaiveDate, interval: u64, days: Vec<i8>) -> impl Fn(NaiveDate) -> bool {
+    let start = month_index(start);
+    let interval = interval as i64;
+
+    move |date| {
+        let day = date.day() as i8;
+        let last = days_in_month(date) as i8;
+
+        (month_index(date) - start) % interval == 0
+            && days
+                .iter()
+                .any(|&wanted| wanted == day || wanted == day - last - 1)
+    }
+}
+
+fn days_in_month(date: NaiveDate) -> u32 {
+    let first = date.with_day(1).expect("bug: every month has a 1st");
+    let next = first
+        .checked_add_months(Months::new(1))
+        .unwrap_or(NaiveDate::MAX);
+
+    (next - first).num_days() as u32
+}
+
+/// Iterates over [`Monthly::all`]
+impl IntoIterator for &Monthly {
+    type Item = SystemTime;
+    type IntoIter = RecurrenceIter;
+
+    fn into_iter(self) -> RecurrenceIter {
+        self.dates().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn dtstart() {
+        let dtstart = july_first();
+
+        let dates = super::Monthly::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            ..Options::default()
+        });
+
+        assert_eq!(dtstart, dates.all().next().unwrap());
+    }
+
+    #[cfg(feature = "local-tz")]
+    #[test]
+    fn quarterly() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 1, 31, 9, 0, 0)
+            .unwrap();
+
+        let dates = super::Monthly::quarterly(dtstart.into(), End::Count(4));
+        let months: Vec<_> = dates
+            .all_local()
+            .map(|date| (date.year(), date.month(), date.day()))
+            .collect();
+
+        // April has no 31st
+        assert_eq!(
+            vec![(2021, 1, 31), (2021, 7, 31), (2021, 10, 31), (2022, 1, 31)],
+            months
+        );
+    }
+
+    #[cfg(feature = "local-tz")]
+    #[test]
+    fn semi_monthly() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 1, 1, 9, 0, 0)
+            .unwrap();
+        let until = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 4, 1, 0, 0, 0)
+            .unwrap();
+
+        let dates = super::Monthly::semi_monthly(dtstart.into(), End::Until(until.into()));
+        let days: Vec<_> = dates
+            .all_local()
+            .map(|date| (date.month(), date.day()))
+            .collect();
+
+        assert_eq!(
+            vec![(1, 1), (1, 15), (2, 1), (2, 15), (3, 1), (3, 15)],
+            days
+        );
+    }
+
+    #[test]
+    fn last_day_of_month() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 1, 10, 9, 0, 0)
+            .unwrap();
+
+        let dates = super::Monthly::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(3),
+            by_month_day: vec![-1],
+            ..Options::default()
+        });
+        let days: Vec<_> = dates.all_local().map(|date| date.day()).collect();
+
+        assert_eq!(vec![31, 28, 31], days);
+    }
+
+    #[test]
+    fn by_month() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 1, 10, 9, 0, 0)
+            .unwrap();
+
+        let dates = super::Monthly::new(Options {
+            dtstart: Some(dtstart.into()),
+            interval: Some(2),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(4),
+            by_month: vec![1, 2, 3, 5],
+            ..Options::default()
+        });
+        let months: Vec<_> = dates
+            .all_local()
+            .map(|date| (date.year(), date.month(), date.day()))
+            .collect();
+
+        // February is never stepped on
+        assert_eq!(
+            vec![(2021, 1, 10), (2021, 3, 10), (2021, 5, 10), (2022, 1, 10)],
+            months
+        );
+
+        let invalid = super::Monthly::try_new(Options {
+            by_month: vec![0],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::InvalidMonth(0)), invalid.err());
+
+        // April never has a 31st
+        let never = super::Monthly::try_new(Options {
+            dtstart: Some(
+                chrono_tz::UTC
+                    .with_ymd_and_hms(2021, 1, 31, 9, 0, 0)
+                    .unwrap()
+                    .into(),
+            ),
+            timezone: Some(chrono_tz::UTC.into()),
+            by_month: vec![4],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::NoMatchingDate), never.err());
+    }
+
+    #[test]
+    fn invalid_month_day() {
+        let dates = super::Monthly::try_new(Options {
+            by_month_day: vec![1, 32],
+            ..Options::default()
+        });
+
+        assert_eq!(Some(RecurrenceError::InvalidMonthDay(32)), dates.err());
+
+        // every 12 months from February only ever lands in February
+        let never = super::Monthly::try_new(Options {
+            dtstart: Some(
+                chrono_tz::UTC
+                    .with_ymd_and_hms(2021, 2, 10, 9, 0, 0)
+                    .unwrap()
+                    .into(),
+            ),
+            interval: Some(12),
+            timezone: Some(chrono_tz::UTC.into()),
+            by_month_day: vec![30],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::NoMatchingDate), never.err());
+    }
+
+    #[test]
+    fn after_the_last_representable_month() {
+        let dates = super::Monthly::new(Options {
+            dtstart: Some(july_first()),
+            timezone: Some(chrono_tz::Asia::Tokyo.into()),
+            ..Options::default()
+        });
+        // chrono's last instant, 262143-12-31T23:59:59Z, and some 35 million
+        // years
+        let last = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(8_210_266_876_799);
+        let far = std::time::Duration::from_secs(1 << 50);
+
+        assert_eq!(None, dates.first_after(last - 20 * ONE_DAY));
+        assert_eq!(None, dates.first_after(SystemTime::UNIX_EPOCH + far));
+        assert_eq!(
+            Some(july_first()),
+            dates.first_after(SystemTime::UNIX_EPOCH - far)
+        );
+    }
+
+    #[test]
+    fn multiple_months() {
+        let dtstart = july_first();
+        let dates = super::Monthly::utc(dtstart, 2, End::Never);
+        let mut dates = dates.all().skip(1);
+
+        // July and August have 31 days each
+        assert_eq!(dtstart + 62 * ONE_DAY, dates.next().unwrap());
+        // September and October have 30 and 31
+        assert_eq!(dtstart + 123 * ONE_DAY, dates.next().unwrap());
+    }
+
+    #[test]
+    fn short_months_left_out() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2020, 1, 31, 9, 0, 0)
+            .unwrap();
+        let dates = super::Monthly::utc(dtstart.into(), 1, End::Count(4));
+
+        let months: Vec<_> = dates.all_local().map(|d| d.month()).collect();
+        assert_eq!(vec![1, 3, 5, 7], months);
+        assert!(dates.all_local().all(|d| d.day() == 31));
+
+        let back: Vec<_> = dates
+            .all_bounded()
+            .unwrap()
+            .rev()
+            .map(|d| DateTime::<chrono::Utc>::from(d).month())
+            .collect();
+        assert_eq!(vec![7, 5, 3, 1], back);
+    }
+
+    #[test]
+    fn keeps_wall_clock_across_dst() {
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 10, 15, 9, 0, 0)
+            .unwrap();
+        let dates = super::Monthly::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            ..Options::default()
+        });
+
+        let november = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 11, 15, 9, 0, 0)
+            .unwrap();
+        assert_eq!(SystemTime::from(november), dates.all().nth(1).unwrap());
+    }
+
+    #[test]
+    fn zero_interval() {
+        let dates = super::Monthly::try_new(Options {
+            interval: Some(0),
+            ..Options::default()
+        });
+
+        assert_eq!(Some(RecurrenceError::ZeroInterval), dates.err());
+    }
+
+    #[test]
+    fn after() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2020, 1, 31, 9, 0, 0)
+            .unwrap();
+        let dates = super::Monthly::utc(dtstart.into(), 1, End::Count(4));
+
+        let min = chrono_tz::UTC
+            .with_ymd_and_hms(2020, 3, 31, 10, 0, 0)
+            .unwrap();
+        let may = chrono_tz::UTC
+            .with_ymd_and_hms(2020, 5, 31, 9, 0, 0)
+            .unwrap();
+
+        let mut after = dates.enumerate_after(min.into());
+        assert_eq!(Some((2, may.into())), after.next());
+        assert_eq!(1, after.count());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{Custom, Daily, Monthly, RRule, Weekly, Yearly};
+use std::time::SystemTime;
+
+/// The occurrences of a recurrence, boxed so they can be returned from a
+/// trait object. Still `Send` and `Sync` like the iterators they box.
+pub type BoxedDates = Box<dyn Iterator<Item = SystemTime> + Send + Sync>;
+
+/// Any recurrence, for code that works the same whatever the frequency
+///
+/// ```
+/// use recurrence::{Daily, End, Recurrence, Weekly};
+/// use std::time::SystemTime;
+///
+/// let rules: Vec<Box<dyn Recurrence>> = vec![
+///     Box::new(Daily::utc(SystemTime::UNIX_EPOCH, 1, End::Count(2))),
+///     Box::new(Weekly::utc(SystemTime::UNIX_EPOCH, 1, End::Count(2))),
+/// ];
+///
+/// let count: usize = rules.iter().map(|rule| rule.all().count()).sum();
+/// assert_eq!(4, count);
+/// ```
+pub trait Recurrence {
+    fn all(&self) -> BoxedDates;
+
+    /// The occurrences at or after `min`
+    fn after(&self, min: SystemTime) -> BoxedDates;
+}
+
+impl Recurrence for Custom {
+    fn all(&self) -> BoxedDates {
+        Box::new(Custom::all(self))
+    }
+
+    fn after(&self, min: SystemTime) -> BoxedDates {
+        Box::new(Custom::after(self, min))
+    }
+}
+
+impl Recurrence for Daily {
+    fn all(&self) -> BoxedDates {
+        Box::new(Daily::all(self))
+    }
+
+    fn after(&self, min: SystemTime) -> BoxedDates {
+        Box::new(Daily::after(self, min))
+    }
+}
+
+impl Recurrence for Weekly {
+    fn all(&self) -> BoxedDates {
+        Box::new(Weekly::all(self))
+    }
+
+    fn after(&self, min: SystemTime) -> BoxedDates {
+        Box::new(Weekly::after(self, min))
+    }
+}
+
+impl Recurrence for Monthly {
+    fn all(&self) -> BoxedDates {
+        Box::new(Monthly::all(self))
+    }
+
+    fn after(&self, min: SystemTime) -> BoxedDates {
+        Box::new(Monthly::after(self, min))
+    }
+}
+
+impl Recurrence for Yearly {
+    fn all(&self) -> BoxedDates {
+        Box::new(Yearly::all(self))
+    }
+
+    fn after(&self, min: SystemTime) -> BoxedDates {
+        Box::new(Yearly::after(self, min))
+    }
+}
+
+impl Recurrence for RRule {
+    fn all(&self) -> BoxedDates {
+        Box::new(RRule::all(self))
+    }
+
+    fn after(&self, min: SystemTime) -> BoxedDates {
+        Box::new(RRule::after(self, min))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helpers::*, End};
+
+    #[test]
+    fn heterogeneous() {
+        let dtstart = july_first();
+        let rules: Vec<Box<dyn Recurrence>> = vec![
+            Box::new(Daily::utc(dtstart, 1, End::Count(2))),
+            Box::new(Weekly::utc(dtstart, 1, End::Count(2))),
+            Box::new(Custom::utc(dtstart, ONE_HOUR, End::Count(2))),
+            Box::new(RRule::Monthly(Monthly::utc(dtstart, 1, End::Count(2)))),
+        ];
+
+        let next_month = Monthly::utc(dtstart, 1, End::Never).all().nth(1).unwrap();
+        let steps = [ONE_DAY, ONE_WEEK, ONE_HOUR];
+        let mut expected: Vec<SystemTime> = steps.iter().map(|&step| dtstart + step).collect();
+        expected.push(next_month);
+
+        for (rule, &second) in rules.iter().zip(&expected) {
+            assert_eq!(vec![dtstart, second], rule.all().collect::<Vec<_>>());
+            assert_eq!(Some(second), rule.after(dtstart + ONE_MINUTE).next());
+        }
+    }
+}
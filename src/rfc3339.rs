@@ -0,0 +1,108 @@
+//! Serde adapters storing instants as RFC 3339 strings in UTC, e.g.
+//! `2020-07-01T04:04:45Z`, for `#[serde(with = "...")]` on `SystemTime` fields
+//! so configs can be read and edited by hand.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize as _, Deserializer, Serializer};
+use std::time::SystemTime;
+
+fn format(date: SystemTime) -> String {
+    DateTime::<Utc>::from(date).to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn parse<E: serde::de::Error>(date: &str) -> Result<SystemTime, E> {
+    DateTime::parse_from_rfc3339(date)
+        .map(SystemTime::from)
+        .map_err(|e| E::custom(format_args!("invalid RFC 3339 date {:?}: {}", date, e)))
+}
+
+pub fn serialize<S: Serializer>(date: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format(*date))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+    parse(&String::deserialize(deserializer)?)
+}
+
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        date: &Option<SystemTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.serialize_some(&format(*date)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<SystemTime>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|date| parse(&date))
+            .transpose()
+    }
+}
+
+pub mod vec {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        dates: &[SystemTime],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(dates.iter().map(|&date| format(date)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<SystemTime>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|date| parse(date))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{daily, test_helpers::*, Daily, End, RRule};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn before_the_epoch() {
+        let dtstart = SystemTime::UNIX_EPOCH - Duration::from_millis(1500);
+        let rule = RRule::Daily(Daily::new(daily::Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Until(dtstart + ONE_WEEK),
+            ..daily::Options::default()
+        }));
+
+        let json = serde_json::to_string(&rule).unwrap();
+        assert!(
+            json.contains(r#""dtstart":"1969-12-31T23:59:58.500Z""#),
+            "{}",
+            json
+        );
+        assert!(
+            json.contains(r#""until":"1970-01-07T23:59:58.500Z""#),
+            "{}",
+            json
+        );
+
+        let parsed: RRule = serde_json::from_str(&json).unwrap();
+        assert!(rule.all().eq(parsed.all()));
+        assert_eq!(Some(dtstart), parsed.all().next());
+    }
+
+    #[test]
+    fn invalid_date() {
+        let json = r#"{"freq":"daily","timezone":"UTC","dtstart":"yesterday"}"#;
+
+        let error = serde_json::from_str::<RRule>(json).unwrap_err().to_string();
+        assert!(error.contains("invalid RFC 3339 date"), "{}", error);
+    }
+}
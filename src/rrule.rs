@@ -1,22 +1,428 @@
-use std::time::SystemTime;
+use crate::{
+    custom, daily, monthly,
+    tz_date_iterator::{BoundedIterator, TzDateIterator},
+    weekly, yearly, AmbiguousPolicy, Custom, Daily, End, Monthly, NonexistentPolicy,
+    RecurrenceError, TimeZoneSpec, Weekly, Yearly,
+};
+use std::time::{Duration, SystemTime};
 
+/// How often a rule built with [`RRule::try_new`] recurs
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Frequency {
+    Secondly,
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// The options shared by every frequency, for rules whose frequency is only
+/// known at runtime
+#[derive(Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Options {
+    /// How many of the frequency's units go by between occurrences
+    pub interval: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::rfc3339::option"))]
+    pub dtstart: Option<SystemTime>,
+    pub timezone: Option<TimeZoneSpec>,
+    pub end: End,
+    pub dst_ambiguous: AmbiguousPolicy,
+    pub dst_nonexistent: NonexistentPolicy,
+}
+
+/// Any of the supported frequencies
+///
+/// More frequencies may be added, so matching on it needs a wildcard arm.
+///
+/// With the `serde` feature it is stored as the rule's options tagged with
+/// its frequency, e.g. `{"freq": "daily", "interval": 2, ...}`.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "RuleOptions", try_from = "RuleOptions")
+)]
+#[non_exhaustive]
 pub enum RRule {
-    Daily(super::Daily),
-    Weekly(super::Weekly),
+    Custom(Custom),
+    Daily(Daily),
+    Weekly(Weekly),
+    Monthly(Monthly),
+    Yearly(Yearly),
 }
 
 impl RRule {
-    pub fn all(&self) -> impl Iterator<Item = SystemTime> {
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`RRule::try_new`]
+    pub fn new(frequency: Frequency, options: Options) -> Self {
+        Self::try_new(frequency, options).expect("invalid rule options")
+    }
+
+    /// A rule of whichever type fits `frequency`; sub-daily frequencies are
+    /// [`Custom`] rules stepping by that many seconds, minutes or hours
+    pub fn try_new(frequency: Frequency, options: Options) -> Result<Self, RecurrenceError> {
+        let Options {
+            interval,
+            dtstart,
+            timezone,
+            end,
+            dst_ambiguous,
+            dst_nonexistent,
+        } = options;
+
+        let rule = match frequency {
+            Frequency::Secondly | Frequency::Minutely | Frequency::Hourly => {
+                let unit = match frequency {
+                    Frequency::Secondly => 1,
+                    Frequency::Minutely => 60,
+                    _ => 60 * 60,
+                };
+                let secs = interval
+                    .unwrap_or(1)
+                    .checked_mul(unit)
+                    .ok_or(RecurrenceError::IntervalTooLarge)?;
+
+                RRule::Custom(Custom::try_new(custom::Options {
+                    interval: Duration::from_secs(secs),
+                    dtstart,
+                    timezone,
+                    end,
+                    dst_ambiguous,
+                    dst_nonexistent,
+                })?)
+            }
+            Frequency::Daily => RRule::Daily(Daily::try_new(daily::Options {
+                interval,
+                dtstart,
+                timezone,
+                end,
+                dst_ambiguous,
+                dst_nonexistent,
+                ..daily::Options::default()
+            })?),
+            Frequency::Weekly => RRule::Weekly(Weekly::try_new(weekly::Options {
+                interval,
+                dtstart,
+                timezone,
+                end,
+                dst_ambiguous,
+                dst_nonexistent,
+                ..weekly::Options::default()
+            })?),
+            Frequency::Monthly => RRule::Monthly(Monthly::try_new(monthly::Options {
+                interval,
+                dtstart,
+                timezone,
+                end,
+                dst_ambiguous,
+                dst_nonexistent,
+                ..monthly::Options::default()
+            })?),
+            Frequency::Yearly => RRule::Yearly(Yearly::try_new(yearly::Options {
+                interval,
+                dtstart,
+                timezone,
+                end,
+                dst_ambiguous,
+                dst_nonexistent,
+                ..yearly::Options::default()
+            })?),
+        };
+
+        Ok(rule)
+    }
+
+    pub fn all(&self) -> RecurrenceIter {
+        let dates = match self {
+            RRule::Custom(c) => c.dates(),
+            RRule::Daily(d) => d.dates(),
+            RRule::Weekly(w) => w.dates(),
+            RRule::Monthly(m) => m.dates(),
+            RRule::Yearly(y) => y.dates(),
+        };
+
+        RecurrenceIter { dates }
+    }
+
+    /// Like [`RRule::all`] but also walkable from the back, only for rules
+    /// whose end is bounded
+    pub fn all_bounded(&self) -> Option<BoundedIterator> {
+        self.all().dates.bounded()
+    }
+
+    pub fn after(&self, min: SystemTime) -> RecurrenceIter {
+        let (_, dates) = match self {
+            RRule::Custom(c) => c.resume(min),
+            RRule::Daily(d) => d.resume(min),
+            RRule::Weekly(w) => w.resume(min),
+            RRule::Monthly(m) => m.resume(min),
+            RRule::Yearly(y) => y.resume(min),
+        };
+
+        RecurrenceIter { dates }
+    }
+
+    pub fn first_after(&self, min: SystemTime) -> Option<SystemTime> {
         match self {
-            RRule::Daily(d) => Box::new(d.all()) as Box<dyn Iterator<Item = _>>,
-            RRule::Weekly(w) => Box::new(w.all()),
+            RRule::Custom(c) => c.first_after(min),
+            RRule::Daily(d) => d.first_after(min),
+            RRule::Weekly(w) => w.first_after(min),
+            RRule::Monthly(m) => m.first_after(min),
+            RRule::Yearly(y) => y.first_after(min),
         }
     }
 
-    pub fn after(&self, min: SystemTime) -> impl Iterator<Item = SystemTime> {
+    /// Whether the occurrences are date-only, each on the midnight starting
+    /// its day
+    pub(crate) fn is_all_day(&self) -> bool {
         match self {
-            RRule::Daily(d) => Box::new(d.after(min)) as Box<dyn Iterator<Item = _>>,
-            RRule::Weekly(w) => Box::new(w.after(min)),
+            RRule::Daily(d) => d.is_all_day(),
+            RRule::Weekly(w) => w.is_all_day(),
+            RRule::Custom(_) | RRule::Monthly(_) | RRule::Yearly(_) => false,
+        }
+    }
+
+    /// Whether the rule has an occurrence at exactly `date`
+    pub fn contains(&self, date: SystemTime) -> bool {
+        self.first_after(date) == Some(date)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "freq", rename_all = "lowercase")]
+enum RuleOptions {
+    Custom(custom::Options),
+    Daily(daily::Options),
+    Weekly(weekly::Options),
+    Monthly(monthly::Options),
+    Yearly(yearly::Options),
+}
+
+#[cfg(feature = "serde")]
+impl From<RRule> for RuleOptions {
+    fn from(rule: RRule) -> RuleOptions {
+        match rule {
+            RRule::Custom(c) => RuleOptions::Custom(c.to_options()),
+            RRule::Daily(d) => RuleOptions::Daily(d.to_options()),
+            RRule::Weekly(w) => RuleOptions::Weekly(w.to_options()),
+            RRule::Monthly(m) => RuleOptions::Monthly(m.to_options()),
+            RRule::Yearly(y) => RuleOptions::Yearly(y.to_options()),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<RuleOptions> for RRule {
+    type Error = RecurrenceError;
+
+    fn try_from(options: RuleOptions) -> Result<RRule, Self::Error> {
+        let rule = match options {
+            RuleOptions::Custom(o) => RRule::Custom(Custom::try_new(o)?),
+            RuleOptions::Daily(o) => RRule::Daily(Daily::try_new(o)?),
+            RuleOptions::Weekly(o) => RRule::Weekly(Weekly::try_new(o)?),
+            RuleOptions::Monthly(o) => RRule::Monthly(Monthly::try_new(o)?),
+            RuleOptions::Yearly(o) => RRule::Yearly(Yearly::try_new(o)?),
+        };
+
+        Ok(rule)
+    }
+}
+
+/// The occurrences of an [`RRule`], or of a rule iterated by reference,
+/// whichever its frequency
+///
+/// Every frequency steps through the same kind of iterator underneath, so
+/// there is nothing to box or dispatch on while merging rules in a `Set`.
+#[derive(Clone)]
+pub struct RecurrenceIter {
+    dates: TzDateIterator,
+}
+
+impl From<TzDateIterator> for RecurrenceIter {
+    fn from(dates: TzDateIterator) -> Self {
+        RecurrenceIter { dates }
+    }
+}
+
+impl Iterator for RecurrenceIter {
+    type Item = SystemTime;
+
+    fn next(&mut self) -> Option<SystemTime> {
+        self.dates.next().map(SystemTime::from)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<SystemTime> {
+        self.dates.nth(n).map(SystemTime::from)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.dates.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helpers::*, Custom, Daily, End, Monthly, Weekly, Yearly};
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    #[test]
+    fn iterators_are_send_and_sync() {
+        let dtstart = july_first();
+        let daily = Daily::utc(dtstart, 1, End::Never);
+        let rule = RRule::Daily(daily.clone());
+        let set = crate::Set::new().rrule(RRule::Daily(daily.clone()));
+
+        assert_send_sync(&rule.all());
+        assert_send_sync(&rule.after(dtstart));
+        assert_send_sync(&daily.all());
+        assert_send_sync(&daily.after(dtstart));
+        assert_send_sync(&daily.all_local());
+        assert_send_sync(&Weekly::utc(dtstart, 1, End::Never).all());
+        assert_send_sync(&Monthly::utc(dtstart, 1, End::Never).all());
+        assert_send_sync(&Yearly::utc(dtstart, 1, End::Never).all());
+        assert_send_sync(&Custom::utc(dtstart, ONE_HOUR, End::Never).all());
+        assert_send_sync(&set.all());
+        assert_send_sync(&set.after(dtstart));
+        assert_send_sync(&set);
+        assert_send_sync(&crate::Recurrence::all(&daily));
+    }
+
+    #[test]
+    fn runtime_frequency() {
+        let dtstart = july_first();
+        let options = || Options {
+            dtstart: Some(dtstart),
+            interval: Some(2),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(3),
+            ..Options::default()
+        };
+
+        let expected = [
+            (
+                Frequency::Hourly,
+                RRule::Custom(Custom::utc(dtstart, 2 * ONE_HOUR, End::Count(3))),
+            ),
+            (
+                Frequency::Daily,
+                RRule::Daily(Daily::utc(dtstart, 2, End::Count(3))),
+            ),
+            (
+                Frequency::Weekly,
+                RRule::Weekly(Weekly::utc(dtstart, 2, End::Count(3))),
+            ),
+            (
+                Frequency::Monthly,
+                RRule::Monthly(Monthly::utc(dtstart, 2, End::Count(3))),
+            ),
+            (
+                Frequency::Yearly,
+                RRule::Yearly(Yearly::utc(dtstart, 2, End::Count(3))),
+            ),
+        ];
+
+        for (frequency, rule) in expected {
+            assert!(RRule::new(frequency, options()).all().eq(rule.all()));
         }
     }
+
+    #[test]
+    fn after_outlives_the_rule() {
+        let dtstart = july_first();
+        let skip = [dtstart + ONE_DAY];
+
+        let dates = {
+            let rule = RRule::Daily(Daily::utc(dtstart, 1, End::Count(3)));
+            rule.after(dtstart)
+        };
+        let dates: Vec<_> = dates.filter(|date| !skip.contains(date)).collect();
+
+        assert_eq!(vec![dtstart, dtstart + 2 * ONE_DAY], dates);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let dtstart = july_first();
+        let rules = vec![
+            RRule::Daily(Daily::utc(dtstart, 2, End::Count(5))),
+            RRule::Weekly(Weekly::new(crate::weekly::Options {
+                dtstart: Some(dtstart),
+                timezone: Some(chrono_tz::US::Eastern.into()),
+                end: End::Until(dtstart + 10 * ONE_WEEK),
+                ..crate::weekly::Options::default()
+            })),
+            RRule::Custom(Custom::utc(dtstart, 90 * ONE_MINUTE, End::Never)),
+            RRule::Monthly(Monthly::utc(dtstart, 1, End::Count(3))),
+            RRule::Yearly(Yearly::new(crate::yearly::Options {
+                dtstart: Some(dtstart),
+                timezone: Some(chrono::FixedOffset::east_opt(5 * 3600).unwrap().into()),
+                end: End::Count(3),
+                ..crate::yearly::Options::default()
+            })),
+        ];
+
+        let json = serde_json::to_string(&rules).unwrap();
+        assert!(json.contains(r#""freq":"daily""#));
+        assert!(json.contains(r#""timezone":"US/Eastern""#));
+
+        let parsed: Vec<RRule> = serde_json::from_str(&json).unwrap();
+        assert_eq!(rules.len(), parsed.len());
+        for (rule, parsed) in rules.iter().zip(&parsed) {
+            assert!(rule.all().take(10).eq(parsed.all().take(10)));
+        }
+
+        let invalid = r#"{"freq":"daily","interval":0,"timezone":"UTC"}"#;
+        let err = serde_json::from_str::<RRule>(invalid).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("interval must be greater than zero"));
+    }
+
+    #[test]
+    fn same_as_each_frequency() {
+        let dtstart = july_first();
+        let min = dtstart + 45 * ONE_DAY;
+
+        let custom = Custom::utc(dtstart, 7 * ONE_HOUR, End::Count(20));
+        let daily = Daily::utc(dtstart, 2, End::Count(20));
+        let weekly = Weekly::utc(dtstart, 1, End::Count(20));
+        let monthly = Monthly::utc(dtstart, 1, End::Count(20));
+        let yearly = Yearly::utc(dtstart, 1, End::Count(20));
+
+        assert!(RRule::Custom(custom.clone()).all().eq(custom.all()));
+        assert!(RRule::Custom(custom.clone())
+            .after(min)
+            .eq(custom.after(min)));
+        assert!(RRule::Daily(daily.clone()).all().eq(daily.all()));
+        assert!(RRule::Daily(daily.clone()).after(min).eq(daily.after(min)));
+        assert!(RRule::Weekly(weekly.clone()).all().eq(weekly.all()));
+        assert!(RRule::Weekly(weekly.clone())
+            .after(min)
+            .eq(weekly.after(min)));
+        assert!(RRule::Monthly(monthly.clone()).all().eq(monthly.all()));
+        assert!(RRule::Monthly(monthly.clone())
+            .after(min)
+            .eq(monthly.after(min)));
+        assert!(RRule::Yearly(yearly.clone()).all().eq(yearly.all()));
+        assert!(RRule::Yearly(yearly.clone())
+            .after(min)
+            .eq(yearly.after(min)));
+    }
 }
@@ -1,9 +1,56 @@
-use crate::RRule;
-use std::time::SystemTime;
+use crate::{RRule, RecurrenceIter};
+use std::{
+    borrow::{Borrow, BorrowMut},
+    cmp::Reverse,
+    collections::{binary_heap::PeekMut, BinaryHeap},
+    fmt,
+    time::SystemTime,
+};
 
-#[derive(Default)]
+/// The occurrences of several rules merged in order, plus any extra dates
+/// (RDATE) and minus any excluded ones (EXDATE)
+///
+/// Occurrences sort by instant. All-day occurrences are on the local
+/// midnight starting their day, so they come before the timed ones of that
+/// day in the same timezone. When an all-day rule and a timed one land on
+/// the same instant, e.g. both at midnight, the all-day occurrence comes
+/// first and is the one [`Set::all_tagged`] reports.
+///
+/// With the `serde` feature it is stored as its list of rules and dates,
+/// e.g. `{"rules": [{"freq": "daily", ...}], "rdates": [], "exdates": []}`.
+#[derive(Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "SetRules")
+)]
 pub struct Set {
     rules: Vec<RRule>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::rfc3339::vec"))]
+    /// sorted and without repeats
+    rdates: Vec<SystemTime>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::rfc3339::vec"))]
+    /// sorted and without repeats
+    exdates: Vec<SystemTime>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SetRules {
+    rules: Vec<RRule>,
+    #[serde(default, with = "crate::rfc3339::vec")]
+    rdates: Vec<SystemTime>,
+    #[serde(default, with = "crate::rfc3339::vec")]
+    exdates: Vec<SystemTime>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SetRules> for Set {
+    fn from(set: SetRules) -> Set {
+        let rules = set.rules.into_iter().fold(Set::new(), Set::rrule);
+        let rdates = set.rdates.into_iter().fold(rules, Set::rdate);
+        set.exdates.into_iter().fold(rdates, Set::exdate)
+    }
 }
 
 impl Set {
@@ -16,52 +63,361 @@ impl Set {
         self
     }
 
+    /// Adds a single occurrence outside of any rule
+    pub fn rdate(mut self, date: SystemTime) -> Self {
+        if let Err(i) = self.rdates.binary_search(&date) {
+            self.rdates.insert(i, date);
+        }
+        self
+    }
+
+    /// Leaves out the occurrence at exactly `date`, whether it comes from a
+    /// rule or an rdate
+    pub fn exdate(mut self, date: SystemTime) -> Self {
+        if let Err(i) = self.exdates.binary_search(&date) {
+            self.exdates.insert(i, date);
+        }
+        self
+    }
+
+    pub(crate) fn rules(&self) -> &[RRule] {
+        &self.rules
+    }
+
+    pub(crate) fn rdates(&self) -> &[SystemTime] {
+        &self.rdates
+    }
+
+    pub(crate) fn exdates(&self) -> &[SystemTime] {
+        &self.exdates
+    }
+
     pub fn all(&self) -> impl Iterator<Item = SystemTime> {
-        self.merge_recurrences(RRule::all)
+        self.merge_recurrences(RRule::all, 0)
     }
 
     pub fn after(&self, min: SystemTime) -> impl Iterator<Item = SystemTime> {
-        self.merge_recurrences(move |r| r.after(min))
+        let rdates = self.rdates.partition_point(|&date| date < min);
+        self.merge_recurrences(move |r| r.after(min), rdates)
+    }
+
+    /// The occurrence at index `n`, same as `all().nth(n)`.
+    ///
+    /// Only a set of a single rule and no rdates jumps to it: the rule's own
+    /// nth is pushed further by the exdates it lands on, without walking the
+    /// occurrences in between when its step is fixed (see
+    /// [`RecurrenceIter`]). Any other set walks its merged dates one at a
+    /// time, no faster than `all().nth(n)`: rules and rdates can share
+    /// instants, which are only counted once, and there is no telling how
+    /// many they share without walking them.
+    pub fn nth(&self, n: usize) -> Option<SystemTime> {
+        let rule = match (&self.rules[..], &self.rdates[..]) {
+            ([rule], []) => rule,
+            _ => return self.all().nth(n),
+        };
+
+        // the occurrence n places past how many of the ones up to it are
+        // excluded; a fixpoint, and the first one found is never excluded
+        let mut excluded = 0;
+        loop {
+            let date = rule.all().nth(n.checked_add(excluded)?)?;
+            let up_to = self.exdates.partition_point(|&exdate| exdate <= date);
+            let now_excluded = self.exdates[..up_to]
+                .iter()
+                .filter(|&&exdate| rule.contains(exdate))
+                .count();
+
+            if now_excluded == excluded {
+                return Some(date);
+            }
+            excluded = now_excluded;
+        }
+    }
+
+    /// Whether the set has an occurrence at exactly `date`: one of the rules
+    /// or rdates lands on it and no exdate leaves it out
+    pub fn contains(&self, date: SystemTime) -> bool {
+        self.exdates.binary_search(&date).is_err()
+            && (self.rdates.binary_search(&date).is_ok()
+                || self.rules.iter().any(|rule| rule.contains(date)))
+    }
+
+    /// The instants where both `a` and `b` have an occurrence, in order.
+    ///
+    /// Finding the next one walks both rules until they meet, so when
+    /// neither rule ends and they never (or no longer) meet, `next()` never
+    /// returns. Bound at least one of them if that can happen.
+    pub fn intersection(a: &RRule, b: &RRule) -> impl Iterator<Item = SystemTime> {
+        intersect_sorted(a.all(), b.all())
+    }
+
+    /// The occurrences of `a` that aren't occurrences of `b`, in order, i.e.
+    /// `a` with `b` as an exclusion rule
+    pub fn difference(a: &RRule, b: &RRule) -> impl Iterator<Item = SystemTime> {
+        subtract_sorted(a.all(), b.all())
     }
 
+    /// The first instant at or after `min` where both `a` and `b` have an
+    /// occurrence.
+    ///
+    /// Rules that never end may never meet, so the search gives up after
+    /// [`COINCIDENCE_SEARCH_LIMIT`] occurrences of either rule: enough for two
+    /// daily rules whose intervals have no common factor to meet as long as
+    /// their product stays under it.
+    pub fn next_coincidence(a: &RRule, b: &RRule, min: SystemTime) -> Option<SystemTime> {
+        let a = a.after(min).take(COINCIDENCE_SEARCH_LIMIT);
+        let b = b.after(min).take(COINCIDENCE_SEARCH_LIMIT);
+
+        intersect_sorted(a, b).next()
+    }
+
+    /// The next occurrence at or after `min` along with the index of the
+    /// rule that produced it. When several rules share that occurrence, the
+    /// one added first wins. Only rules are looked at: rdates have no rule
+    /// to point to, but exdates are still left out.
+    pub fn next_after(&self, min: SystemTime) -> Option<(usize, SystemTime)> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter_map(|(i, rule)| {
+                rule.after(min)
+                    .find(|date| self.exdates.binary_search(date).is_err())
+                    .map(|date| (i, date))
+            })
+            .min_by_key(|&(_, date)| date)
+    }
+
+    /// Like [`Set::all`] but each occurrence comes with the index of the rule
+    /// that produced it, in the order the rules were added. When several
+    /// rules share an occurrence it comes once, tagged with the rule added
+    /// first. Only rules are looked at: rdates have no rule to point to, but
+    /// exdates are still left out.
+    pub fn all_tagged(&self) -> impl Iterator<Item = (usize, SystemTime)> {
+        let unseeded: Vec<_> = self
+            .rules
+            .iter()
+            .map(|rule| (rule.is_all_day(), rule.all()))
+            .collect();
+        let min_heap = BinaryHeap::with_capacity(unseeded.len());
+        let exdates = self.exdates.clone();
+
+        merge_rules(unseeded.into_iter(), min_heap)
+            .filter(move |(_, date)| exdates.binary_search(date).is_err())
+    }
+
+    /// Like [`Set::all`] but merging in `buffer`, which keeps its
+    /// allocation from one query to the next
+    pub fn all_with<'a>(
+        &'a self,
+        buffer: &'a mut SetBuffer,
+    ) -> impl Iterator<Item = SystemTime> + 'a {
+        buffer.heap.clear();
+        merge(
+            self.rules
+                .iter()
+                .map(|rule| (rule.is_all_day(), rule.all())),
+            &mut buffer.heap,
+            self.rdates.iter().copied(),
+            &self.exdates[..],
+        )
+    }
+
+    /// Like [`Set::after`] but merging in `buffer`, see [`Set::all_with`]
+    pub fn after_with<'a>(
+        &'a self,
+        min: SystemTime,
+        buffer: &'a mut SetBuffer,
+    ) -> impl Iterator<Item = SystemTime> + 'a {
+        let rdates = self.rdates.partition_point(|&date| date < min);
+        buffer.heap.clear();
+        merge(
+            self.rules
+                .iter()
+                .map(move |rule| (rule.is_all_day(), rule.after(min))),
+            &mut buffer.heap,
+            self.rdates[rdates..].iter().copied(),
+            &self.exdates[..],
+        )
+    }
+
+    /// Merges the rules' dates with the rdates from index `rdates` on,
+    /// leaving out the exdates
     fn merge_recurrences<F: Iterator<Item = SystemTime>>(
         &self,
         dates: impl Fn(&RRule) -> F,
+        rdates: usize,
     ) -> impl Iterator<Item = SystemTime> {
-        use std::cmp::Reverse;
-
-        let mut min_heap: std::collections::BinaryHeap<_> = self
+        let unseeded: Vec<_> = self
             .rules
             .iter()
-            .map(dates)
-            .filter_map(|mut iter| {
-                iter.next()
-                    .map(|cursor| Reverse(IterHolder { iter, cursor }))
-            })
+            .map(|rule| (rule.is_all_day(), dates(rule)))
             .collect();
+        let min_heap = BinaryHeap::with_capacity(unseeded.len());
 
-        std::iter::from_fn(move || {
-            while let Some(Reverse(IterHolder { cursor, mut iter })) = min_heap.pop() {
-                if let Some(next) = iter.next() {
-                    min_heap.push(Reverse(IterHolder { cursor: next, iter }))
-                }
+        merge(
+            unseeded.into_iter(),
+            min_heap,
+            self.rdates[rdates..].to_vec(),
+            self.exdates.clone(),
+        )
+    }
+}
+
+/// The heap [`Set::all_with`] and [`Set::after_with`] merge the rules in,
+/// kept around to query a set again without allocating it anew
+#[derive(Default)]
+pub struct SetBuffer {
+    heap: BinaryHeap<Reverse<IterHolder<RecurrenceIter>>>,
+}
+
+impl SetBuffer {
+    pub fn new() -> Self {
+        SetBuffer::default()
+    }
+}
 
-                if let Some(Reverse(IterHolder { cursor: next, .. })) = min_heap.peek() {
-                    if *next == cursor {
-                        continue;
-                    }
+impl fmt::Debug for SetBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SetBuffer")
+            .field("capacity", &self.heap.capacity())
+            .finish()
+    }
+}
+
+/// Merges the dates of each of `rules` in `min_heap`, along with `rdates`
+/// and leaving out `exdates`. Each rule comes with whether it is all-day.
+fn merge<F: Iterator<Item = SystemTime>>(
+    unseeded: impl Iterator<Item = (bool, F)>,
+    min_heap: impl BorrowMut<BinaryHeap<Reverse<IterHolder<F>>>>,
+    rdates: impl IntoIterator<Item = SystemTime>,
+    exdates: impl Borrow<[SystemTime]>,
+) -> impl Iterator<Item = SystemTime> {
+    let rules = merge_rules(unseeded, min_heap).map(|(_, date)| date);
+
+    merge_sorted(rules, rdates.into_iter())
+        .filter(move |date| exdates.borrow().binary_search(date).is_err())
+}
+
+/// Merges the dates of each of `rules` in `min_heap`, each along with the
+/// index of the rule it came from. Each rule comes with whether it is all-day.
+fn merge_rules<F: Iterator<Item = SystemTime>>(
+    unseeded: impl Iterator<Item = (bool, F)>,
+    mut min_heap: impl BorrowMut<BinaryHeap<Reverse<IterHolder<F>>>>,
+) -> impl Iterator<Item = (usize, SystemTime)> {
+    let mut unseeded = unseeded.enumerate();
+
+    std::iter::from_fn(move || {
+        let min_heap = min_heap.borrow_mut();
+        // each rule's first date waits until the merged dates are polled
+        for (index, (all_day, mut iter)) in unseeded.by_ref() {
+            if let Some(cursor) = iter.next() {
+                min_heap.push(Reverse(IterHolder {
+                    index,
+                    all_day,
+                    cursor,
+                    iter,
+                }));
+            }
+        }
+
+        let Reverse(IterHolder {
+            index,
+            all_day,
+            cursor,
+            mut iter,
+        }) = min_heap.pop()?;
+        if let Some(next) = iter.next() {
+            min_heap.push(Reverse(IterHolder {
+                index,
+                all_day,
+                cursor: next,
+                iter,
+            }))
+        }
+
+        // ties pop all-day rules and then the lowest index first, so that
+        // is the one emitted and every other rule sharing this date just
+        // moves past it
+        while let Some(mut same) = min_heap.peek_mut() {
+            let Reverse(holder) = &mut *same;
+            if holder.cursor != cursor {
+                break;
+            }
+            match holder.iter.next() {
+                Some(next) => holder.cursor = next,
+                None => {
+                    PeekMut::pop(same);
                 }
+            }
+        }
+
+        Some((index, cursor))
+    })
+}
+
+/// Merges two ascending streams of dates, yielding dates found in both once
+fn merge_sorted(
+    first: impl Iterator<Item = SystemTime>,
+    second: impl Iterator<Item = SystemTime>,
+) -> impl Iterator<Item = SystemTime> {
+    let mut first = first.peekable();
+    let mut second = second.peekable();
 
-                return Some(cursor);
+    std::iter::from_fn(move || match (first.peek(), second.peek()) {
+        (Some(a), Some(b)) if b < a => second.next(),
+        (Some(a), Some(b)) if a == b => {
+            second.next();
+            first.next()
+        }
+        (Some(_), _) => first.next(),
+        (None, _) => second.next(),
+    })
+}
+
+/// How many occurrences of each rule [`Set::next_coincidence`] walks before
+/// giving up
+pub const COINCIDENCE_SEARCH_LIMIT: usize = 100_000;
+
+/// The dates of two ascending streams that are found in both
+fn intersect_sorted(
+    first: impl Iterator<Item = SystemTime>,
+    second: impl Iterator<Item = SystemTime>,
+) -> impl Iterator<Item = SystemTime> {
+    let mut first = first.peekable();
+    let mut second = second.peekable();
+
+    std::iter::from_fn(move || loop {
+        match (first.peek(), second.peek()) {
+            (Some(a), Some(b)) if a < b => first.next(),
+            (Some(a), Some(b)) if b < a => second.next(),
+            (Some(_), Some(_)) => {
+                second.next();
+                return first.next();
             }
+            _ => return None,
+        };
+    })
+}
 
-            None
-        })
-    }
+/// The dates of the ascending stream `kept` that aren't in the ascending
+/// stream `left_out`, which is only walked as far as `kept` gets
+fn subtract_sorted(
+    kept: impl Iterator<Item = SystemTime>,
+    left_out: impl Iterator<Item = SystemTime>,
+) -> impl Iterator<Item = SystemTime> {
+    let mut left_out = left_out.peekable();
+
+    kept.filter(move |date| {
+        while left_out.next_if(|excluded| excluded < date).is_some() {}
+        left_out.peek() != Some(date)
+    })
 }
 
-/// Holds an interator and the latest date that came out of it
+/// Holds an interator, the index of the rule it iterates over and the
+/// latest date that came out of it
 pub struct IterHolder<I: Iterator<Item = SystemTime>> {
+    index: usize,
+    all_day: bool,
     cursor: SystemTime,
     iter: I,
 }
@@ -70,7 +426,7 @@ impl<I: Iterator<Item = SystemTime>> Eq for IterHolder<I> {}
 
 impl<I: Iterator<Item = SystemTime>> PartialEq for IterHolder<I> {
     fn eq(&self, other: &Self) -> bool {
-        self.cursor.eq(&other.cursor)
+        self.cmp(other).is_eq()
     }
 }
 
@@ -80,16 +436,17 @@ impl<I: Iterator<Item = SystemTime>> PartialOrd for IterHolder<I> {
     }
 }
 
+/// Earlier dates first, then all-day rules, then the rules added first
 impl<I: Iterator<Item = SystemTime>> Ord for IterHolder<I> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.cursor.cmp(&other.cursor)
+        (self.cursor, !self.all_day, self.index).cmp(&(other.cursor, !other.all_day, other.index))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{daily, weekly, Daily, Weekly};
+    use crate::{daily, weekly, Daily, Monthly, Weekly, Yearly};
     use std::time::Duration;
 
     #[test]
@@ -100,10 +457,12 @@ mod tests {
         let set = Set::new()
             .rrule(RRule::Daily(Daily::new(daily::Options {
                 dtstart: Some(first_start),
+                timezone: Some(chrono_tz::UTC.into()),
                 ..daily::Options::default()
             })))
             .rrule(RRule::Daily(Daily::new(daily::Options {
                 dtstart: Some(day_and_a_half_before),
+                timezone: Some(chrono_tz::UTC.into()),
                 ..daily::Options::default()
             })));
 
@@ -127,10 +486,12 @@ mod tests {
         let set = Set::new()
             .rrule(RRule::Daily(Daily::new(daily::Options {
                 dtstart: Some(start),
+                timezone: Some(chrono_tz::UTC.into()),
                 ..daily::Options::default()
             })))
             .rrule(RRule::Weekly(Weekly::new(weekly::Options {
                 dtstart: Some(start),
+                timezone: Some(chrono_tz::UTC.into()),
                 ..weekly::Options::default()
             })));
 
@@ -142,4 +503,406 @@ mod tests {
             start + Duration::from_secs(24 * 60 * 60)
         );
     }
+
+    #[test]
+    fn skips_repeated_across_three_rules() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        let set = Set::new()
+            .rrule(RRule::Daily(Daily::new(daily::Options {
+                dtstart: Some(start),
+                timezone: Some(chrono_tz::UTC.into()),
+                ..daily::Options::default()
+            })))
+            .rrule(RRule::Weekly(Weekly::new(weekly::Options {
+                dtstart: Some(start),
+                timezone: Some(chrono_tz::UTC.into()),
+                ..weekly::Options::default()
+            })))
+            .rrule(RRule::Daily(Daily::new(daily::Options {
+                dtstart: Some(start),
+                timezone: Some(chrono_tz::UTC.into()),
+                interval: Some(2),
+                ..daily::Options::default()
+            })));
+
+        let mut all = set.all();
+        assert_eq!(all.next().unwrap(), start);
+        assert_eq!(
+            all.next().unwrap(),
+            start + Duration::from_secs(24 * 60 * 60)
+        );
+        assert_eq!(
+            all.next().unwrap(),
+            start + Duration::from_secs(2 * 24 * 60 * 60)
+        );
+    }
+
+    #[test]
+    fn lazy_until_polled() {
+        use std::{cell::Cell, rc::Rc};
+
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let set = Set::new()
+            .rrule(RRule::Daily(Daily::utc(start, 1, crate::End::Never)))
+            .rrule(RRule::Weekly(Weekly::utc(
+                start + Duration::from_secs(60 * 60),
+                1,
+                crate::End::Never,
+            )));
+
+        let pulled = Rc::new(Cell::new(0));
+        let counted = pulled.clone();
+        let mut all = set.merge_recurrences(
+            move |rule| {
+                let counted = counted.clone();
+                rule.all().inspect(move |_| counted.set(counted.get() + 1))
+            },
+            0,
+        );
+        assert_eq!(0, pulled.get());
+
+        // one date from each rule to seed, then the next one of the rule
+        // that went first
+        assert_eq!(Some(start), all.next());
+        assert_eq!(3, pulled.get());
+    }
+
+    #[test]
+    fn reused_buffer() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let day = Duration::from_secs(24 * 60 * 60);
+        let set = Set::new()
+            .rrule(RRule::Daily(Daily::utc(start, 2, crate::End::Count(5))))
+            .rrule(RRule::Weekly(Weekly::utc(start, 1, crate::End::Count(3))))
+            .rdate(start + day / 2)
+            .exdate(start + 2 * day);
+
+        let mut buffer = SetBuffer::new();
+        for _ in 0..2 {
+            assert!(set.all_with(&mut buffer).eq(set.all()));
+            assert!(set
+                .after_with(start + day, &mut buffer)
+                .eq(set.after(start + day)));
+        }
+        // a query dropped halfway leaves nothing behind for the next one
+        assert_eq!(Some(start), set.all_with(&mut buffer).next());
+        assert!(set.all_with(&mut buffer).eq(set.all()));
+    }
+
+    #[test]
+    fn nth() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let day = Duration::from_secs(24 * 60 * 60);
+        let daily = RRule::Daily(Daily::utc(start, 1, crate::End::Count(1_000)));
+
+        let single = Set::new()
+            .rrule(daily.clone())
+            .exdate(start)
+            .exdate(start + 3 * day)
+            // not an occurrence, so it leaves nothing out
+            .exdate(start + day / 2)
+            .exdate(start + 4 * day);
+        let several = Set::new()
+            .rrule(daily)
+            .rrule(RRule::Weekly(Weekly::utc(
+                start + day / 2,
+                1,
+                crate::End::Count(5),
+            )))
+            .rdate(start + day / 3)
+            .exdate(start + day);
+
+        for set in &[single, several] {
+            for &n in &[0, 1, 2, 3, 500, 995, 996, 1_100] {
+                assert_eq!(set.all().nth(n), set.nth(n), "{}", n);
+            }
+        }
+    }
+
+    #[test]
+    fn contains() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let set = Set::new()
+            .rrule(RRule::Daily(Daily::utc(start, 1, crate::End::Never)))
+            .rrule(RRule::Weekly(Weekly::utc(
+                start + day / 2,
+                1,
+                crate::End::Never,
+            )))
+            .rdate(start + day / 4)
+            .exdate(start + 4 * day);
+
+        // only the weekly rule lands on it
+        assert!(set.contains(start + 7 * day + day / 2));
+        // only the daily one
+        assert!(set.contains(start + 2 * day));
+        assert!(set.contains(start + day / 4));
+        assert!(!set.contains(start + day / 3));
+        assert!(!set.contains(start + 4 * day));
+    }
+
+    #[test]
+    fn all_tagged() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let set = Set::new()
+            .rrule(RRule::Weekly(Weekly::utc(
+                start + day / 2,
+                1,
+                crate::End::Count(2),
+            )))
+            .rrule(RRule::Daily(Daily::utc(start, 3, crate::End::Count(4))))
+            .rdate(start + day / 4)
+            .exdate(start + 3 * day);
+
+        let tagged: Vec<_> = set.all_tagged().collect();
+        assert_eq!(
+            vec![
+                (1, start),
+                (0, start + day / 2),
+                (1, start + 6 * day),
+                (0, start + 7 * day + day / 2),
+                (1, start + 9 * day),
+            ],
+            tagged
+        );
+    }
+
+    #[test]
+    fn all_tagged_coinciding() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let set = Set::new()
+            .rrule(RRule::Weekly(Weekly::utc(start, 1, crate::End::Count(2))))
+            .rrule(RRule::Daily(Daily::utc(start, 1, crate::End::Count(8))));
+
+        let tagged: Vec<_> = set.all_tagged().collect();
+        let mut expected: Vec<_> = (0..8).map(|i| (1, start + i * day)).collect();
+        expected[0].0 = 0;
+        expected[7].0 = 0;
+        assert_eq!(expected, tagged);
+
+        // the first rule wins whichever is iterated first
+        let set = Set::new()
+            .rrule(RRule::Daily(Daily::utc(start, 1, crate::End::Count(8))))
+            .rrule(RRule::Weekly(Weekly::utc(start, 1, crate::End::Count(2))));
+        assert!(set.all_tagged().all(|(index, _)| index == 0));
+        assert_eq!(8, set.all_tagged().count());
+    }
+
+    #[test]
+    fn all_day_before_timed() {
+        use chrono::{TimeZone as _, Timelike as _};
+
+        let new_york = chrono_tz::America::New_York;
+        let daily = |hour, minute| {
+            Daily::builder()
+                .zoned_dtstart(
+                    new_york
+                        .with_ymd_and_hms(2021, 3, 1, hour, minute, 0)
+                        .unwrap(),
+                )
+                .count(25)
+        };
+        let all_day = || RRule::Daily(daily(15, 0).all_day().build().unwrap());
+        let at = |hour, minute| RRule::Daily(daily(hour, minute).build().unwrap());
+
+        // clocks spring forward on the 14th and the all-day rule still lands
+        // on the midnights, before the 00:30s
+        let set = Set::new().rrule(at(0, 30)).rrule(all_day());
+        let times: Vec<_> = set
+            .all()
+            .map(|date| chrono::DateTime::<chrono::Utc>::from(date).with_timezone(&new_york))
+            .map(|date| (date.hour(), date.minute()))
+            .collect();
+        assert_eq!([(0, 0), (0, 30)].repeat(25), times);
+
+        // a timed rule at midnight added first still gives way to the
+        // all-day one
+        let set = Set::new().rrule(at(0, 0)).rrule(at(0, 30)).rrule(all_day());
+        let tagged: Vec<_> = set.all_tagged().map(|(index, _)| index).collect();
+        assert_eq!([2, 1].repeat(25), tagged);
+    }
+
+    #[test]
+    fn intersection() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let daily = RRule::Daily(Daily::utc(start, 1, crate::End::Never));
+        let weekly = RRule::Weekly(Weekly::utc(start, 1, crate::End::Count(4)));
+
+        let both: Vec<_> = Set::intersection(&daily, &weekly).collect();
+        assert_eq!(weekly.all().collect::<Vec<_>>(), both);
+        assert!(Set::intersection(&weekly, &daily).eq(both));
+    }
+
+    #[test]
+    fn difference() {
+        // 1970-01-04 was a Sunday
+        let sunday = SystemTime::UNIX_EPOCH + Duration::from_secs(3 * 24 * 60 * 60);
+        let day = Duration::from_secs(24 * 60 * 60);
+        let daily = RRule::Daily(Daily::utc(sunday - 2 * day, 1, crate::End::Count(14)));
+        let sundays = RRule::Weekly(Weekly::utc(sunday, 1, crate::End::Never));
+
+        let weekdays: Vec<_> = Set::difference(&daily, &sundays).collect();
+
+        assert_eq!(12, weekdays.len());
+        assert!(!weekdays.contains(&sunday));
+        assert!(!weekdays.contains(&(sunday + 7 * day)));
+        assert!(weekdays.contains(&(sunday + day)));
+    }
+
+    #[test]
+    fn next_coincidence() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let day = Duration::from_secs(24 * 60 * 60);
+        let every_other = RRule::Daily(Daily::utc(start, 2, crate::End::Never));
+        let every_third = RRule::Daily(Daily::utc(start, 3, crate::End::Never));
+
+        assert_eq!(
+            Some(start),
+            Set::next_coincidence(&every_other, &every_third, start)
+        );
+        assert_eq!(
+            Some(start + 6 * day),
+            Set::next_coincidence(&every_other, &every_third, start + day)
+        );
+        assert_eq!(
+            Some(start + 12 * day),
+            Set::next_coincidence(&every_third, &every_other, start + 12 * day)
+        );
+
+        let coincidences: Vec<_> = Set::intersection(&every_other, &every_third)
+            .take(3)
+            .collect();
+        assert_eq!(vec![start, start + 6 * day, start + 12 * day], coincidences);
+
+        // an hour apart, so they never meet
+        let offset = RRule::Daily(Daily::utc(
+            start + Duration::from_secs(60 * 60),
+            1,
+            crate::End::Never,
+        ));
+        assert_eq!(None, Set::next_coincidence(&every_other, &offset, start));
+    }
+
+    #[test]
+    fn next_after() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let min = start + Duration::from_secs(36 * 60 * 60);
+
+        let set = Set::new()
+            .rrule(RRule::Weekly(Weekly::new(weekly::Options {
+                dtstart: Some(start),
+                timezone: Some(chrono_tz::UTC.into()),
+                ..weekly::Options::default()
+            })))
+            .rrule(RRule::Daily(Daily::new(daily::Options {
+                dtstart: Some(start),
+                timezone: Some(chrono_tz::UTC.into()),
+                ..daily::Options::default()
+            })));
+
+        let (index, next) = set.next_after(min).unwrap();
+        assert_eq!(1, index);
+        assert_eq!(set.after(min).next().unwrap(), next);
+    }
+
+    #[test]
+    fn debug() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        let set = Set::new()
+            .rrule(RRule::Daily(Daily::utc(start, 2, crate::End::Never)))
+            .rrule(RRule::Weekly(Weekly::utc(start, 5, crate::End::Never)));
+        let debug = format!("{:?}", set);
+
+        assert!(debug.contains("Daily"));
+        assert!(debug.contains("interval: 2"));
+        assert!(debug.contains("Weekly"));
+        assert!(debug.contains("interval: 5"));
+    }
+
+    #[test]
+    fn all_frequencies() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let set = Set::new()
+            .rrule(RRule::Daily(Daily::utc(start, 1, crate::End::Count(3))))
+            .rrule(RRule::Weekly(Weekly::utc(
+                start + day / 2,
+                1,
+                crate::End::Count(2),
+            )))
+            .rrule(RRule::Monthly(Monthly::utc(
+                start + day / 4,
+                1,
+                crate::End::Count(2),
+            )))
+            .rrule(RRule::Yearly(Yearly::utc(
+                start + day / 8,
+                1,
+                crate::End::Count(2),
+            )));
+
+        let all: Vec<_> = set.all().collect();
+        assert_eq!(9, all.len());
+        assert!(all.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(start + 365 * day + day / 8, *all.last().unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        let set = Set::new()
+            .rrule(RRule::Daily(Daily::utc(start, 2, crate::End::Count(10))))
+            .rrule(RRule::Weekly(Weekly::utc(
+                start + Duration::from_secs(60 * 60),
+                1,
+                crate::End::Count(4),
+            )));
+
+        let json = serde_json::to_string(&set).unwrap();
+        let parsed: Set = serde_json::from_str(&json).unwrap();
+
+        assert!(set.all().eq(parsed.all()));
+        assert_eq!(14, parsed.all().count());
+    }
+
+    #[test]
+    fn rdates_and_exdates() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let set = Set::new()
+            .rrule(RRule::Daily(Daily::utc(start, 1, crate::End::Count(4))))
+            .rdate(start + day / 2)
+            .rdate(start + 2 * day)
+            .rdate(start - day)
+            .exdate(start + day)
+            .exdate(start + day / 2 + day);
+
+        assert_eq!(
+            vec![
+                start - day,
+                start,
+                start + day / 2,
+                start + 2 * day,
+                start + 3 * day
+            ],
+            set.all().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![start + day / 2, start + 2 * day, start + 3 * day],
+            set.after(start + day / 4).collect::<Vec<_>>()
+        );
+        assert_eq!(Some((0, start + 2 * day)), set.next_after(start + day));
+    }
 }
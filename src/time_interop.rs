@@ -0,0 +1,100 @@
+//! Adapters for the `time` crate, behind the `time` feature.
+//!
+//! `dtstart` and `End::Until` can already be set from an `OffsetDateTime`
+//! through `SystemTime::from`; this adds a way back out that keeps the
+//! offset each occurrence has in the rule's timezone.
+
+use crate::{Custom, Daily, Monthly, TimeZoneSpec, Weekly, Yearly};
+use chrono::{DateTime, Offset as _};
+use time::{OffsetDateTime, UtcOffset};
+
+fn to_offset_date_time(date: DateTime<TimeZoneSpec>) -> OffsetDateTime {
+    let offset = UtcOffset::from_whole_seconds(date.offset().fix().local_minus_utc())
+        .expect("bug: chrono offsets are always within a day");
+
+    let nanos = date.timestamp() as i128 * 1_000_000_000 + date.timestamp_subsec_nanos() as i128;
+
+    OffsetDateTime::from_unix_timestamp_nanos(nanos)
+        .expect("bug: chrono dates fit in time's range")
+        .to_offset(offset)
+}
+
+impl Daily {
+    /// Like [`Daily::all_local`] but as `time` dates
+    pub fn all_offset(&self) -> impl Iterator<Item = OffsetDateTime> {
+        self.all_local().map(to_offset_date_time)
+    }
+}
+
+impl Weekly {
+    /// Like [`Weekly::all_local`] but as `time` dates
+    pub fn all_offset(&self) -> impl Iterator<Item = OffsetDateTime> {
+        self.all_local().map(to_offset_date_time)
+    }
+}
+
+impl Monthly {
+    /// Like [`Monthly::all_local`] but as `time` dates
+    pub fn all_offset(&self) -> impl Iterator<Item = OffsetDateTime> {
+        self.all_local().map(to_offset_date_time)
+    }
+}
+
+impl Yearly {
+    /// Like [`Yearly::all_local`] but as `time` dates
+    pub fn all_offset(&self) -> impl Iterator<Item = OffsetDateTime> {
+        self.all_local().map(to_offset_date_time)
+    }
+}
+
+impl Custom {
+    /// Like [`Custom::all_local`] but as `time` dates
+    pub fn all_offset(&self) -> impl Iterator<Item = OffsetDateTime> {
+        self.all_local().map(to_offset_date_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{daily, test_helpers::*, Daily};
+    use std::time::SystemTime;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn round_trip() {
+        let dtstart = OffsetDateTime::from_unix_timestamp(1_572_750_000)
+            .unwrap()
+            .to_offset(time::UtcOffset::from_hms(-4, 0, 0).unwrap());
+
+        let dates = Daily::new(daily::Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            ..daily::Options::default()
+        });
+        let mut dates = dates.all_offset();
+
+        let first = dates.next().unwrap();
+        assert_eq!(dtstart, first);
+        assert_eq!(-4, first.offset().whole_hours());
+
+        // clocks fall back in between, so the offset changes but not the hour
+        let second = dates.next().unwrap();
+        assert_eq!(SystemTime::from(dtstart) + ONE_DAY + ONE_HOUR, second);
+        assert_eq!(-5, second.offset().whole_hours());
+        assert_eq!(first.hour(), second.hour());
+    }
+
+    #[test]
+    fn past_year_9999() {
+        let dtstart = OffsetDateTime::from_unix_timestamp(253_402_214_400).unwrap();
+        let dates = Daily::new(daily::Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: crate::End::Count(3),
+            ..daily::Options::default()
+        });
+
+        let years: Vec<_> = dates.all_offset().map(|date| date.year()).collect();
+        assert_eq!(vec![9999, 10000, 10000], years);
+    }
+}
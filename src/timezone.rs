@@ -0,0 +1,154 @@
+use crate::RecurrenceError;
+use chrono::{FixedOffset, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::{fmt, str::FromStr, sync::OnceLock};
+
+/// The timezone a recurrence is computed in: either a named IANA timezone,
+/// which may observe DST, or a raw UTC offset, which never changes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeZoneSpec {
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl From<Tz> for TimeZoneSpec {
+    fn from(tz: Tz) -> Self {
+        TimeZoneSpec::Named(tz)
+    }
+}
+
+impl From<Utc> for TimeZoneSpec {
+    fn from(_: Utc) -> Self {
+        TimeZoneSpec::Named(Tz::UTC)
+    }
+}
+
+impl From<FixedOffset> for TimeZoneSpec {
+    fn from(offset: FixedOffset) -> Self {
+        TimeZoneSpec::Fixed(offset)
+    }
+}
+
+/// Parses an IANA name, e.g. `Europe/Paris`, or an offset, e.g. `+05:30`
+impl FromStr for TimeZoneSpec {
+    type Err = RecurrenceError;
+
+    fn from_str(name: &str) -> Result<Self, RecurrenceError> {
+        name.parse::<Tz>()
+            .map(TimeZoneSpec::Named)
+            .or_else(|_| name.parse::<FixedOffset>().map(TimeZoneSpec::Fixed))
+            .map_err(|_| RecurrenceError::UnknownTimezone(name.to_owned()))
+    }
+}
+
+/// Every IANA name a [`TimeZoneSpec`] can be parsed from, e.g. to fill a
+/// timezone picker
+pub fn timezone_names() -> &'static [&'static str] {
+    static NAMES: OnceLock<Vec<&'static str>> = OnceLock::new();
+
+    NAMES.get_or_init(|| chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name()).collect())
+}
+
+/// Serialized as the IANA name, e.g. `America/New_York`, or as the offset,
+/// e.g. `+05:30`
+#[cfg(feature = "serde")]
+impl serde::Serialize for TimeZoneSpec {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            TimeZoneSpec::Named(tz) => serializer.serialize_str(tz.name()),
+            TimeZoneSpec::Fixed(offset) => serializer.collect_str(offset),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TimeZoneSpec {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// The offset in effect for a [`TimeZoneSpec`] at a given time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpecOffset {
+    Named(<Tz as TimeZone>::Offset),
+    Fixed(FixedOffset),
+}
+
+impl Offset for SpecOffset {
+    fn fix(&self) -> FixedOffset {
+        match self {
+            SpecOffset::Named(offset) => offset.fix(),
+            SpecOffset::Fixed(offset) => *offset,
+        }
+    }
+}
+
+impl fmt::Display for SpecOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecOffset::Named(offset) => offset.fmt(f),
+            SpecOffset::Fixed(offset) => offset.fmt(f),
+        }
+    }
+}
+
+impl TimeZone for TimeZoneSpec {
+    type Offset = SpecOffset;
+
+    fn from_offset(offset: &SpecOffset) -> Self {
+        match offset {
+            SpecOffset::Named(offset) => TimeZoneSpec::Named(Tz::from_offset(offset)),
+            SpecOffset::Fixed(offset) => TimeZoneSpec::Fixed(*offset),
+        }
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<SpecOffset> {
+        match self {
+            TimeZoneSpec::Named(tz) => tz.offset_from_local_date(local).map(SpecOffset::Named),
+            TimeZoneSpec::Fixed(offset) => {
+                offset.offset_from_local_date(local).map(SpecOffset::Fixed)
+            }
+        }
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<SpecOffset> {
+        match self {
+            TimeZoneSpec::Named(tz) => tz.offset_from_local_datetime(local).map(SpecOffset::Named),
+            TimeZoneSpec::Fixed(offset) => offset
+                .offset_from_local_datetime(local)
+                .map(SpecOffset::Fixed),
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> SpecOffset {
+        match self {
+            TimeZoneSpec::Named(tz) => SpecOffset::Named(tz.offset_from_utc_date(utc)),
+            TimeZoneSpec::Fixed(offset) => SpecOffset::Fixed(offset.offset_from_utc_date(utc)),
+        }
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> SpecOffset {
+        match self {
+            TimeZoneSpec::Named(tz) => SpecOffset::Named(tz.offset_from_utc_datetime(utc)),
+            TimeZoneSpec::Fixed(offset) => SpecOffset::Fixed(offset.offset_from_utc_datetime(utc)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        let names = timezone_names();
+
+        assert!(names.contains(&"UTC"));
+        assert!(names
+            .iter()
+            .all(|name| matches!(name.parse(), Ok(TimeZoneSpec::Named(_)))));
+    }
+}
@@ -0,0 +1,76 @@
+//! Loading a [`Set`] from a TOML config, behind the `toml` feature.
+
+use crate::{RRule, Set};
+
+#[derive(serde::Deserialize)]
+struct Config {
+    #[serde(default)]
+    rule: Vec<RRule>,
+}
+
+impl Set {
+    /// Parses a `[[rule]]` array, where each rule has a `freq` and the
+    /// options of that frequency:
+    ///
+    /// ```toml
+    /// [[rule]]
+    /// freq = "daily"
+    /// interval = 2
+    /// timezone = "Europe/Paris"
+    /// dtstart = "2020-07-01T04:04:45Z"
+    /// end = { count = 10 }
+    /// ```
+    ///
+    /// Errors point at the offending rule, e.g. an unknown timezone name.
+    pub fn from_toml(config: &str) -> Result<Set, toml::de::Error> {
+        let config: Config = toml::from_str(config)?;
+        Ok(config.rule.into_iter().fold(Set::new(), Set::rrule))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_helpers::*, weekly, Daily, End, RRule, Set, Weekly};
+
+    #[test]
+    fn daily_and_weekly() {
+        let config = r#"
+            [[rule]]
+            freq = "daily"
+            interval = 2
+            timezone = "UTC"
+            dtstart = "2020-07-01T04:04:45Z"
+            end = { count = 3 }
+
+            [[rule]]
+            freq = "weekly"
+            timezone = "America/New_York"
+            dtstart = "2020-07-01T04:04:45Z"
+            end = { count = 2 }
+        "#;
+
+        let expected = Set::new()
+            .rrule(RRule::Daily(Daily::utc(july_first(), 2, End::Count(3))))
+            .rrule(RRule::Weekly(Weekly::new(weekly::Options {
+                dtstart: Some(july_first()),
+                timezone: Some(chrono_tz::America::New_York.into()),
+                end: End::Count(2),
+                ..weekly::Options::default()
+            })));
+
+        let set = Set::from_toml(config).unwrap();
+        assert!(set.all().eq(expected.all()));
+    }
+
+    #[test]
+    fn unknown_timezone() {
+        let config = r#"
+            [[rule]]
+            freq = "daily"
+            timezone = "Mars/Phobos"
+        "#;
+
+        let error = Set::from_toml(config).unwrap_err().to_string();
+        assert!(error.contains("unknown timezone: Mars/Phobos"), "{}", error);
+    }
+}
@@ -1,66 +1,669 @@
-use chrono::{DateTime, Duration, NaiveDateTime, Offset as _};
-use chrono_tz::Tz;
-use std::time::SystemTime;
+use crate::{
+    dst::{self, AmbiguousPolicy, NonexistentPolicy, Resolution},
+    End, RecurrenceError, TimeZoneSpec,
+};
+use chrono::{
+    DateTime, Datelike as _, Duration, NaiveDate, NaiveDateTime, NaiveTime, Offset as _,
+    TimeZone as _, Weekday,
+};
+use std::{collections::HashSet, convert::TryFrom, sync::Arc, time::SystemTime};
 
-#[derive(Clone, Copy)]
-pub enum End {
-    Until(NaiveDateTime),
-    Count(usize),
-    Never,
+/// Fails past the range of dates chrono can represent, some 262,000 years
+/// either way from the epoch
+pub fn from_system_to_naive(time: SystemTime) -> Result<NaiveDateTime, RecurrenceError> {
+    let secs = |duration: std::time::Duration| i64::try_from(duration.as_secs()).ok();
+    let (secs, nanos) = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(after) => (secs(after), after.subsec_nanos()),
+        // before the epoch: round the seconds down so the nanos stay positive
+        Err(before) => {
+            let before = before.duration();
+            match before.subsec_nanos() {
+                0 => (secs(before).map(|secs| -secs), 0),
+                nanos => (secs(before).map(|secs| -secs - 1), 1_000_000_000 - nanos),
+            }
+        }
+    };
+
+    secs.and_then(|secs| DateTime::from_timestamp(secs, nanos))
+        .map(|date| date.naive_utc())
+        .ok_or(RecurrenceError::DateOutOfRange)
+}
+
+pub fn from_naive_to_system(time: NaiveDateTime) -> SystemTime {
+    time.and_utc().into()
+}
+
+/// Seconds since the Unix epoch, negative before it
+pub fn from_timestamp(secs: i64) -> SystemTime {
+    let magnitude = std::time::Duration::from_secs(secs.unsigned_abs());
+
+    if secs < 0 {
+        SystemTime::UNIX_EPOCH - magnitude
+    } else {
+        SystemTime::UNIX_EPOCH + magnitude
+    }
+}
+
+/// How far apart consecutive occurrences are in wall-clock time
+#[derive(Clone)]
+pub enum Step {
+    Fixed(Duration),
+    /// Calendar months, keeping dtstart's day of the month. Months too
+    /// short to have that day are left out of the series.
+    Months(u64),
+    /// Days that aren't off
+    WorkingDays(u64, Arc<DaysOff>),
+    /// Each of the listed times of the day, in ascending order, on every day
+    /// the inner step lands on
+    Times(Box<Step>, Arc<[NaiveTime]>),
+    /// Every day that matches, for rules that expand a period into several
+    /// days. The series ends when none does within the given number of
+    /// days, see [`matching_horizon`].
+    Matching(Arc<dyn Fn(NaiveDate) -> bool + Send + Sync>, u64),
 }
 
-impl From<crate::End> for End {
-    fn from(end: crate::End) -> End {
-        match end {
-            crate::End::Never => End::Never,
-            crate::End::Count(count) => End::Count(count),
-            crate::End::Until(until) => End::Until(from_system_to_naive(until)),
+/// The days a daily rule leaves out
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DaysOff {
+    pub weekends: bool,
+    pub holidays: HashSet<NaiveDate>,
+}
+
+impl DaysOff {
+    pub fn is_empty(&self) -> bool {
+        !self.weekends && self.holidays.is_empty()
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        (self.weekends && matches!(date.weekday(), Weekday::Sat | Weekday::Sun))
+            || self.holidays.contains(&date)
+    }
+}
+
+impl Step {
+    /// The wall-clock time `steps` steps away from `local`; `None` past the
+    /// range of dates chrono can represent. A `local` on none of the times
+    /// listed by `Times` moves to the ones around it.
+    pub fn shift(&self, local: NaiveDateTime, steps: i64) -> Option<NaiveDateTime> {
+        match *self {
+            Step::Fixed(interval) => {
+                local.checked_add_signed(interval.checked_mul(i32::try_from(steps).ok()?)?)
+            }
+            Step::Months(months) => {
+                let months = i64::try_from(months).ok()?.checked_mul(steps)?;
+                let mut away = months;
+                loop {
+                    if let Some(shifted) = nth_month(local, away).ok()? {
+                        break Some(shifted);
+                    }
+                    away = away.checked_add(months)?;
+                }
+            }
+            Step::WorkingDays(days, ref off) => {
+                let days = i64::try_from(days).ok()?.checked_mul(steps)?;
+                let direction = Duration::days(days.signum());
+                let mut shifted = local;
+                for _ in 0..days.unsigned_abs() {
+                    shifted = shifted.checked_add_signed(direction)?;
+                    while off.contains(shifted.date()) {
+                        shifted = shifted.checked_add_signed(direction)?;
+                    }
+                }
+                Some(shifted)
+            }
+            Step::Times(ref days, ref times) => {
+                let mut shifted = local;
+                for _ in 0..steps.unsigned_abs() {
+                    let time = shifted.time();
+                    let same_day = match steps {
+                        0.. => times.iter().find(|&&listed| listed > time),
+                        _ => times.iter().rev().find(|&&listed| listed < time),
+                    };
+                    shifted = match same_day {
+                        Some(&listed) => shifted.date().and_time(listed),
+                        None => {
+                            let listed = match steps {
+                                0.. => times.first()?,
+                                _ => times.last()?,
+                            };
+                            days.shift(shifted, steps.signum())?
+                                .date()
+                                .and_time(*listed)
+                        }
+                    };
+                }
+                Some(shifted)
+            }
+            Step::Matching(ref matches, horizon) => {
+                let direction = Duration::days(steps.signum());
+                let mut shifted = local;
+                for _ in 0..steps.unsigned_abs() {
+                    let mut days = 1;
+                    shifted = shifted.checked_add_signed(direction)?;
+                    while !matches(shifted.date()) {
+                        if days >= horizon {
+                            return None;
+                        }
+                        days += 1;
+                        shifted = shifted.checked_add_signed(direction)?;
+                    }
+                }
+                Some(shifted)
+            }
         }
     }
 }
 
-fn from_system_to_naive(time: SystemTime) -> NaiveDateTime {
-    let duration = time.duration_since(SystemTime::UNIX_EPOCH).expect("bug");
-    NaiveDateTime::from_timestamp(duration.as_secs() as i64, duration.subsec_nanos())
+impl From<Duration> for Step {
+    fn from(duration: Duration) -> Step {
+        Step::Fixed(duration)
+    }
+}
+
+/// The same day of the month and time as `local`, `months` months away;
+/// `Ok(None)` when that month is too short to have the day
+pub fn nth_month(
+    local: NaiveDateTime,
+    months: i64,
+) -> Result<Option<NaiveDateTime>, RecurrenceError> {
+    let index = month_index(local.date())
+        .checked_add(months)
+        .ok_or(RecurrenceError::DateOutOfRange)?;
+    let year = i32::try_from(index.div_euclid(12))
+        .ok()
+        .filter(|&year| NaiveDate::from_ymd_opt(year, 1, 1).is_some())
+        .ok_or(RecurrenceError::DateOutOfRange)?;
+    let month = index.rem_euclid(12) as u32 + 1;
+
+    Ok(NaiveDate::from_ymd_opt(year, month, local.day()).map(|date| date.and_time(local.time())))
+}
+
+/// How many days a rule stepping `months` months at a time can go without
+/// a matching day before none ever comes: the Gregorian calendar repeats
+/// every 4800 months, so every month the rule lands on comes back within
+/// 4800 steps
+pub fn matching_horizon(months: u64) -> u64 {
+    months.saturating_mul(4800 * 31).saturating_add(31)
+}
+
+/// Months since year zero
+pub fn month_index(date: NaiveDate) -> i64 {
+    date.year() as i64 * 12 + date.month0() as i64
+}
+
+#[cfg(all(test, feature = "local-tz"))]
+thread_local! {
+    /// Lets tests simulate platforms where the local timezone can't be found
+    pub static FAIL_LOCAL_TZ: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// The system's timezone, or UTC when it can't be resolved (some containers, wasm)
+#[cfg(feature = "local-tz")]
+pub fn local_tz() -> Option<TimeZoneSpec> {
+    let local = lookup_local_tz().unwrap_or_else(|| {
+        log::warn!("could not resolve the local timezone, defaulting to UTC");
+        chrono_tz::UTC
+    });
+
+    Some(TimeZoneSpec::Named(local))
+}
+
+/// Without the `local-tz` feature there is no default timezone to fall back on
+#[cfg(not(feature = "local-tz"))]
+pub fn local_tz() -> Option<TimeZoneSpec> {
+    None
+}
+
+#[cfg(feature = "local-tz")]
+fn lookup_local_tz() -> Option<chrono_tz::Tz> {
+    #[cfg(test)]
+    {
+        if FAIL_LOCAL_TZ.with(std::cell::Cell::get) {
+            return None;
+        }
+    }
+
+    // without a JS host to ask there is no timezone to find, and reaching
+    // for one traps instead of erroring
+    if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+        return None;
+    }
+
+    iana_time_zone::get_timezone().ok()?.parse().ok()
 }
 
 /// Timezone Aware Date Iterator
+///
+/// Occurrences keep the same wall-clock time across offset changes (i.e.
+/// DST); each one is stepped in local time and then placed in the timezone.
+///
+/// Iterating from the back is only possible when the end is bounded
+/// (`Count` or either `Until`); an iterator that never ends yields nothing from
+/// the back.
+#[derive(Clone)]
 pub struct TzDateIterator {
-    pub end: End,
-    pub cursor: DateTime<Tz>,
-    pub interval: Duration,
+    end: End,
+    /// the first occurrence of the series, always yielded as is
+    dtstart: DateTime<TimeZoneSpec>,
+    /// wall-clock time of the next occurrence
+    cursor: NaiveDateTime,
+    step: Step,
+    ambiguous: AmbiguousPolicy,
+    nonexistent: NonexistentPolicy,
+    /// wall-clock time of the last occurrence not yet yielded, computed on
+    /// the first call to `next_back`
+    back: Option<NaiveDateTime>,
 }
 
-impl Iterator for TzDateIterator {
-    type Item = SystemTime;
+impl TzDateIterator {
+    pub fn new(dtstart: DateTime<TimeZoneSpec>, step: impl Into<Step>, end: End) -> Self {
+        TzDateIterator {
+            end,
+            dtstart,
+            cursor: dtstart.naive_local(),
+            step: step.into(),
+            ambiguous: AmbiguousPolicy::default(),
+            nonexistent: NonexistentPolicy::default(),
+            back: None,
+        }
+    }
+
+    /// Resumes the series at the wall-clock time `cursor` instead of dtstart
+    pub fn starting_at(mut self, cursor: NaiveDateTime) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    pub fn ambiguous(mut self, ambiguous: AmbiguousPolicy) -> Self {
+        self.ambiguous = ambiguous;
+        self
+    }
+
+    pub fn nonexistent(mut self, nonexistent: NonexistentPolicy) -> Self {
+        self.nonexistent = nonexistent;
+        self
+    }
+
+    /// Resumes the series at the occurrences at or after `min`, along with
+    /// how many were skipped. `skip` counts them off for a `min` after
+    /// dtstart, given in dtstart's timezone, and returns the wall-clock time
+    /// of the first one left. Running past the range of dates chrono can
+    /// represent, there or with `min` itself, leaves no occurrence.
+    pub fn resume(
+        mut self,
+        min: SystemTime,
+        skip: impl FnOnce(DateTime<TimeZoneSpec>) -> Option<(usize, NaiveDateTime)>,
+    ) -> (usize, Self) {
+        let timezone = self.dtstart.timezone();
+        let resumed = match from_system_to_naive(min) {
+            Ok(min) if min <= self.dtstart.naive_utc() => Some((0, self.cursor)),
+            Ok(min) => min
+                .checked_add_offset(timezone.offset_from_utc_datetime(&min).fix())
+                .and_then(|_| skip(timezone.from_utc_datetime(&min))),
+            Err(_) if min < SystemTime::UNIX_EPOCH => Some((0, self.cursor)),
+            Err(_) => None,
+        };
+
+        match resumed {
+            Some((skipped, cursor)) => {
+                self.end.skip(skipped);
+                self.cursor = cursor;
+                (skipped, self)
+            }
+            None => {
+                self.end = End::Count(0);
+                (0, self)
+            }
+        }
+    }
+
+    /// Skips the occurrences before `min` one at a time, along with how many
+    /// there were, for steps that can't be counted off the calendar
+    pub fn skip_before(mut self, min: SystemTime) -> (usize, Self) {
+        let mut skipped = 0;
+
+        loop {
+            let rest = self.clone();
+            match self.next() {
+                Some(date) if SystemTime::from(date) < min => skipped += 1,
+                _ => return (skipped, rest),
+            }
+        }
+    }
+
+    /// How many occurrences are left, `None` when the series never ends.
+    ///
+    /// Steps of whole days are counted off the calendar: the wall-clock time
+    /// of the until gives the number of steps up to it, and only the
+    /// occurrences right around it are placed to see which side they land
+    /// on. Any other step, or DST policies that leave occurrences out, are
+    /// walked one at a time.
+    pub fn estimated_count(self) -> Option<usize> {
+        let (count, until, inclusive) = match self.end {
+            End::Never => return None,
+            End::Count(count) => (count, None, false),
+            End::Until(until) => (usize::MAX, Some(until), false),
+            End::UntilInclusive(until) => (usize::MAX, Some(until), true),
+            End::CountOrUntil { count, until } => (count, Some(until), false),
+            End::AfterDuration(_) => unreachable!("bug: rules resolve the end when built"),
+        };
+
+        let days = match self.step {
+            Step::Fixed(interval) if interval == Duration::days(interval.num_days()) => {
+                interval.num_days()
+            }
+            _ => 0,
+        };
+        let placed = days > 0
+            && self.ambiguous != AmbiguousPolicy::Error
+            && self.nonexistent == NonexistentPolicy::ShiftForward;
+        if !placed {
+            return Some(self.count());
+        }
+        let until = match until {
+            Some(until) => until,
+            None => return Some(count),
+        };
+
+        let before_until = |steps: i64| {
+            let local = match steps {
+                0.. => self.shift(self.cursor, steps),
+                _ => None,
+            };
+            match local.map(|local| self.resolve(local)) {
+                Some(Resolution::At(date)) if inclusive => SystemTime::from(date) <= until,
+                Some(Resolution::At(date)) => SystemTime::from(date) < until,
+                _ => false,
+            }
+        };
+
+        let until_local = self
+            .dtstart
+            .timezone()
+            .from_utc_datetime(
+                &from_system_to_naive(until).expect("bug: ends are checked when built"),
+            )
+            .naive_local();
+        let mut last = (until_local - self.cursor)
+            .num_days()
+            .div_euclid(days)
+            .max(-1);
+        // the offset at the until can differ from the one at the occurrences
+        // around it by a few hours, which is at most a step either way
+        while last >= 0 && !before_until(last) {
+            last -= 1;
+        }
+        while before_until(last + 1) {
+            last += 1;
+        }
+
+        let until_count = usize::try_from(last + 1).expect("bug: last is at least -1");
+        Some(count.min(until_count))
+    }
+
+    /// Only when the end is a count that no DST policy can cut short
+    pub fn counted(self) -> Option<CountedIterator> {
+        let stops_early = self.ambiguous == AmbiguousPolicy::Error
+            || self.nonexistent == NonexistentPolicy::Error;
+
+        match self.end {
+            End::Count(_) if !stops_early => Some(CountedIterator { dates: self }),
+            _ => None,
+        }
+    }
+
+    /// Only when the end is bounded; a series that never ends has no back
+    pub fn bounded(self) -> Option<BoundedIterator> {
+        match self.end {
+            End::Never => None,
+            _ => Some(BoundedIterator { dates: self }),
+        }
+    }
+
+    /// Whether `count` more occurrences are sure to come before the series
+    /// runs past the range of dates chrono can represent. Only fixed steps
+    /// that land on every wall-clock time are checked; the others can skip
+    /// ahead or give up searching.
+    fn reaches(&self, count: usize) -> bool {
+        if count == 0 {
+            return true;
+        }
+        if !matches!(self.step, Step::Fixed(_)) || self.nonexistent == NonexistentPolicy::Skip {
+            return false;
+        }
+
+        i64::try_from(count - 1)
+            .ok()
+            .and_then(|steps| self.shift(self.cursor, steps))
+            .is_some()
+    }
+
+    fn resolve(&self, local: NaiveDateTime) -> Resolution {
+        if local == self.dtstart.naive_local() {
+            Resolution::At(self.dtstart)
+        } else {
+            dst::resolve(
+                self.dtstart.timezone(),
+                local,
+                self.ambiguous,
+                self.nonexistent,
+            )
+        }
+    }
+
+    /// The wall-clock time `steps` steps away from `local`, which must be on
+    /// an occurrence; `None` past the range of dates chrono can represent
+    fn shift(&self, local: NaiveDateTime, steps: i64) -> Option<NaiveDateTime> {
+        self.step.shift(local, steps)
+    }
+
+    /// Moves the cursor one step forward, ending the series when that step
+    /// would leave the representable range
+    fn step_forward(&mut self) {
+        match self.shift(self.cursor, 1) {
+            Some(cursor) => self.cursor = cursor,
+            None => self.end = End::Count(0),
+        }
+    }
+
+    fn advance(&mut self) -> Option<DateTime<TimeZoneSpec>> {
+        let current = loop {
+            if let End::Count(0) | End::CountOrUntil { count: 0, .. } = self.end {
+                return None;
+            }
+
+            match self.resolve(self.cursor) {
+                Resolution::At(current) => break current,
+                Resolution::Skip => self.step_forward(),
+                Resolution::Stop => {
+                    self.end = End::Count(0);
+                    return None;
+                }
+            }
+        };
+
+        let instant = SystemTime::from(current);
+        match self.end {
+            End::Until(until) if until <= instant => return None,
+            End::UntilInclusive(until) if until < instant => return None,
+            End::CountOrUntil { until, .. } if until <= instant => return None,
+            End::Count(ref mut count) | End::CountOrUntil { ref mut count, .. } => *count -= 1,
+            _ => {}
+        }
+
+        self.step_forward();
+        Some(current)
+    }
+
+    /// Finds the last occurrence by walking forward from the cursor, so the
+    /// first call takes as long as iterating the whole series. Once found,
+    /// the end is turned into the count of occurrences left so both ends of
+    /// the iterator know when they meet.
+    fn seek_back(&mut self) -> Option<NaiveDateTime> {
+        if self.back.is_none() {
+            if let End::Never = self.end {
+                return None;
+            }
+
+            let mut forward = self.clone();
+            let mut count = 0;
+            loop {
+                let local = forward.cursor;
+                if forward.advance().is_none() {
+                    break;
+                }
+                count += 1;
+                self.back = Some(local);
+            }
+
+            self.end = End::Count(count);
+        }
+
+        self.back
+    }
+
+    /// The occurrence at the back of the series, stepping the back one
+    /// occurrence closer to the front
+    fn retreat(&mut self) -> Option<DateTime<TimeZoneSpec>> {
+        let back = self.seek_back()?;
 
-    fn next(&mut self) -> Option<SystemTime> {
         match self.end {
             End::Count(0) => return None,
-            End::Until(until) if until < self.cursor.naive_utc() => return None,
             End::Count(ref mut count) => *count -= 1,
-            _ => {}
+            _ => unreachable!("bug: seek_back always leaves a count"),
         }
 
-        let mut next = self.cursor + self.interval;
+        // walking forward already placed this occurrence
+        let current = match self.resolve(back) {
+            Resolution::At(current) => current,
+            _ => unreachable!("bug: seek_back only stops on placed occurrences"),
+        };
+
+        // stepping back from dtstart may leave the representable range, but
+        // then the count has already run out
+        let mut back = self.shift(back, -1);
+        while let Some(Resolution::Skip) = back.map(|local| self.resolve(local)) {
+            back = back.and_then(|local| self.shift(local, -1));
+        }
+        self.back = back;
+
+        Some(current)
+    }
+}
+
+impl Iterator for TzDateIterator {
+    type Item = DateTime<TimeZoneSpec>;
+
+    fn next(&mut self) -> Option<DateTime<TimeZoneSpec>> {
+        self.advance()
+    }
+
+    /// Jumps straight to the nth occurrence for a fixed step, as long as
+    /// every occurrence up to it is sure to be placed
+    fn nth(&mut self, n: usize) -> Option<DateTime<TimeZoneSpec>> {
+        let jumps = matches!(self.step, Step::Fixed(_))
+            && self.back.is_none()
+            && self.ambiguous != AmbiguousPolicy::Error
+            && self.nonexistent == NonexistentPolicy::ShiftForward;
+        if !jumps {
+            for _ in 0..n {
+                self.advance()?;
+            }
+            return self.advance();
+        }
 
-        if next.offset() != self.cursor.offset() {
-            let difference = chrono::Duration::seconds(
-                (next.offset().fix().local_minus_utc()
-                    - self.cursor.offset().fix().local_minus_utc()) as i64,
-            );
-            next = next - difference;
+        if let End::Count(ref mut count) | End::CountOrUntil { ref mut count, .. } = self.end {
+            if *count <= n {
+                *count = 0;
+                return None;
+            }
+            *count -= n;
+        }
+        match i64::try_from(n)
+            .ok()
+            .and_then(|n| self.shift(self.cursor, n))
+        {
+            Some(cursor) => self.cursor = cursor,
+            None => self.end = End::Count(0),
         }
 
-        let current = std::mem::replace(&mut self.cursor, next);
-        Some(current.into())
+        self.advance()
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        match self.end {
-            End::Until(_) => (0, None),
+        let (lower, upper) = match self.end {
+            End::Until(_) | End::UntilInclusive(_) => (0, None),
             End::Count(n) => (n, Some(n)),
+            End::CountOrUntil { count, .. } => (0, Some(count)),
             End::Never => (usize::MAX, None),
+            End::AfterDuration(_) => unreachable!("bug: rules resolve the end when built"),
+        };
+
+        // either policy can stop the series short at any occurrence
+        if self.ambiguous == AmbiguousPolicy::Error || self.nonexistent == NonexistentPolicy::Error
+        {
+            return (0, upper);
         }
+
+        match self.end {
+            End::Count(count) if !self.reaches(count) => (0, upper),
+            _ => (lower, upper),
+        }
+    }
+}
+
+/// Occurrences of a rule that ends after a set count, so there are never
+/// more than that many left. There can be fewer: the series still stops
+/// at the end of the range of dates chrono can represent.
+#[derive(Clone)]
+pub struct CountedIterator {
+    dates: TzDateIterator,
+}
+
+impl Iterator for CountedIterator {
+    type Item = SystemTime;
+
+    fn next(&mut self) -> Option<SystemTime> {
+        self.dates.next().map(SystemTime::from)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.dates.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for CountedIterator {
+    fn next_back(&mut self) -> Option<SystemTime> {
+        self.dates.retreat().map(SystemTime::from)
+    }
+}
+
+/// Occurrences of a rule with a bounded end, which can also be taken from
+/// the back. The first one taken from the back walks the whole series
+/// forward to find where it ends; the ones after it are a step each.
+#[derive(Clone)]
+pub struct BoundedIterator {
+    dates: TzDateIterator,
+}
+
+impl Iterator for BoundedIterator {
+    type Item = SystemTime;
+
+    fn next(&mut self) -> Option<SystemTime> {
+        self.dates.next().map(SystemTime::from)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<SystemTime> {
+        self.dates.nth(n).map(SystemTime::from)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.dates.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for BoundedIterator {
+    fn next_back(&mut self) -> Option<SystemTime> {
+        self.dates.retreat().map(SystemTime::from)
     }
 }
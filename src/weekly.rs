@@ -1,55 +1,344 @@
-use crate::{tz_date_iterator::TzDateIterator, End};
-use chrono::{Datelike as _, Duration, NaiveDateTime, TimeZone as _};
+use crate::{
+    describe,
+    dst::{self, Resolution},
+    tz_date_iterator::{
+        from_naive_to_system, from_system_to_naive, from_timestamp, local_tz, BoundedIterator,
+        TzDateIterator,
+    },
+    AmbiguousPolicy, End, NonexistentPolicy, RecurrenceError, RecurrenceIter, TimeZoneSpec,
+};
+use chrono::{DateTime, Datelike as _, Duration, NaiveDateTime, TimeZone as _, Weekday};
 use chrono_tz::Tz;
-use std::time::SystemTime;
+use std::{convert::TryFrom, time::SystemTime};
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Weekly {
-    interval: u32,
-    timezone: Tz,
+    interval: u64,
+    timezone: TimeZoneSpec,
     dtstart: NaiveDateTime,
     end: End,
+    dst_ambiguous: AmbiguousPolicy,
+    dst_nonexistent: NonexistentPolicy,
+    all_day: bool,
 }
 
-#[derive(Default)]
+#[derive(Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Options {
-    pub interval: Option<u32>,
-    pub timezone: Option<Tz>,
+    pub interval: Option<u64>,
+    pub timezone: Option<TimeZoneSpec>,
+    /// The timezone by name, e.g. `Europe/Paris` or `+05:30`, parsed when
+    /// the rule is built. Takes precedence over `timezone`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub timezone_name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::rfc3339::option"))]
     pub dtstart: Option<SystemTime>,
     pub end: End,
+    pub dst_ambiguous: AmbiguousPolicy,
+    pub dst_nonexistent: NonexistentPolicy,
+    /// Date-only occurrences: each one lands on the local midnight starting
+    /// its day, whatever the UTC offset is then. The DST policies don't
+    /// apply: a midnight DST skips or repeats moves to the day's first
+    /// instant.
+    pub all_day: bool,
+    /// Moves the first occurrence to the next of this weekday on or after
+    /// dtstart, keeping dtstart's wall-clock time
+    pub start_weekday: Option<Weekday>,
+}
+
+/// Builds a [`Weekly`] one option at a time
+///
+/// ```
+/// use recurrence::{weekly, Weekly, End};
+/// use std::time::{Duration, SystemTime};
+///
+/// let dtstart = SystemTime::UNIX_EPOCH + Duration::from_secs(1_593_576_285);
+///
+/// let built = Weekly::builder()
+///     .dtstart(dtstart)
+///     .interval(2)
+///     .timezone(chrono_tz::UTC)
+///     .count(3)
+///     .build()
+///     .unwrap();
+///
+/// let literal = Weekly::new(weekly::Options {
+///     dtstart: Some(dtstart),
+///     interval: Some(2),
+///     timezone: Some(chrono_tz::UTC.into()),
+///     end: End::Count(3),
+///     ..weekly::Options::default()
+/// });
+///
+/// assert!(built.all().eq(literal.all()));
+/// ```
+#[derive(Debug, Default)]
+pub struct Builder {
+    options: Options,
+}
+
+impl Builder {
+    pub fn interval(mut self, interval: u64) -> Self {
+        self.options.interval = Some(interval);
+        self
+    }
+
+    pub fn timezone(mut self, timezone: impl Into<TimeZoneSpec>) -> Self {
+        self.options.timezone = Some(timezone.into());
+        self
+    }
+
+    /// Sets the timezone by name, see [`Options::timezone_name`]
+    pub fn timezone_name(mut self, name: impl Into<String>) -> Self {
+        self.options.timezone_name = Some(name.into());
+        self
+    }
+
+    pub fn dtstart(mut self, dtstart: SystemTime) -> Self {
+        self.options.dtstart = Some(dtstart);
+        self
+    }
+
+    /// Sets the dtstart from seconds since the Unix epoch, negative before it
+    pub fn dtstart_timestamp(self, secs: i64) -> Self {
+        self.dtstart(from_timestamp(secs))
+    }
+
+    /// Sets both the dtstart and the timezone from a chrono `DateTime`
+    pub fn zoned_dtstart<Z>(self, dtstart: DateTime<Z>) -> Self
+    where
+        Z: chrono::TimeZone + Into<TimeZoneSpec>,
+    {
+        self.timezone(dtstart.timezone()).dtstart(dtstart.into())
+    }
+
+    pub fn count(mut self, count: usize) -> Self {
+        self.options.end = self.options.end.with_count(count);
+        self
+    }
+
+    pub fn until(mut self, until: SystemTime) -> Self {
+        self.options.end = self.options.end.with_until(until);
+        self
+    }
+
+    pub fn dst_ambiguous(mut self, policy: AmbiguousPolicy) -> Self {
+        self.options.dst_ambiguous = policy;
+        self
+    }
+
+    pub fn dst_nonexistent(mut self, policy: NonexistentPolicy) -> Self {
+        self.options.dst_nonexistent = policy;
+        self
+    }
+
+    pub fn all_day(mut self) -> Self {
+        self.options.all_day = true;
+        self
+    }
+
+    pub fn start_weekday(mut self, weekday: Weekday) -> Self {
+        self.options.start_weekday = Some(weekday);
+        self
+    }
+
+    pub fn build(self) -> Result<Weekly, RecurrenceError> {
+        Weekly::try_new(self.options)
+    }
 }
 
 impl Weekly {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Weekly::try_new`]
     pub fn new(options: Options) -> Self {
-        Weekly {
-            dtstart: from_system_to_naive(options.dtstart.unwrap_or_else(|| SystemTime::now())),
-            timezone: options.timezone.unwrap_or_else(local_tz),
-            interval: options.interval.unwrap_or(1),
-            end: options.end,
+        Self::try_new(options).expect("invalid weekly options")
+    }
+
+    /// A recurrence computed in UTC, without looking up the local timezone
+    ///
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Weekly::try_new`]
+    pub fn utc(dtstart: SystemTime, interval: u64, end: End) -> Self {
+        Self::new(Options {
+            dtstart: Some(dtstart),
+            interval: Some(interval),
+            timezone: Some(chrono_tz::UTC.into()),
+            end,
+            ..Options::default()
+        })
+    }
+
+    /// Every other week in the local timezone
+    ///
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Weekly::try_new`]
+    pub fn biweekly(dtstart: SystemTime, end: End) -> Self {
+        Self::new(Options {
+            dtstart: Some(dtstart),
+            interval: Some(2),
+            end,
+            ..Options::default()
+        })
+    }
+
+    pub fn try_new(options: Options) -> Result<Self, RecurrenceError> {
+        let dtstart = options.dtstart.unwrap_or_else(SystemTime::now);
+        let interval = options.interval.unwrap_or(1);
+
+        if interval == 0 {
+            return Err(RecurrenceError::ZeroInterval);
         }
+        // a step that long could not be taken even once
+        i64::try_from(interval)
+            .ok()
+            .and_then(chrono::Duration::try_weeks)
+            .ok_or(RecurrenceError::IntervalTooLarge)?;
+        let end = options.end.resolve(dtstart)?;
+
+        let mut dtstart = from_system_to_naive(dtstart)?;
+        let timezone = match options.timezone_name {
+            Some(name) => Some(name.parse()?),
+            None => options.timezone,
+        };
+        let timezone = timezone
+            .or_else(local_tz)
+            .ok_or(RecurrenceError::MissingTimezone)?;
+        if let Some(weekday) = options.start_weekday {
+            dtstart = dst::first_day(timezone, dtstart, |date| date.weekday() == weekday, 6)?;
+        }
+        let (dst_ambiguous, dst_nonexistent) = if options.all_day {
+            dst::ALL_DAY_POLICIES
+        } else {
+            (options.dst_ambiguous, options.dst_nonexistent)
+        };
+        if options.all_day {
+            dtstart = dst::all_day(timezone, dtstart);
+        }
+
+        Ok(Weekly {
+            dtstart,
+            timezone,
+            interval,
+            end,
+            dst_ambiguous,
+            dst_nonexistent,
+            all_day: options.all_day,
+        })
     }
 
-    pub fn all(&self) -> impl Iterator<Item = SystemTime> {
-        TzDateIterator {
-            end: self.end.into(),
-            cursor: self.timezone.from_utc_datetime(&self.dtstart),
-            interval: chrono::Duration::weeks(self.interval as i64),
+    /// The options that build this same rule back
+    pub fn to_options(&self) -> Options {
+        Options {
+            interval: Some(self.interval),
+            timezone: Some(self.timezone),
+            timezone_name: None,
+            dtstart: Some(from_naive_to_system(self.dtstart)),
+            end: self.end,
+            dst_ambiguous: self.dst_ambiguous,
+            dst_nonexistent: self.dst_nonexistent,
+            all_day: self.all_day,
+            start_weekday: None,
         }
     }
 
+    pub fn all(&self) -> impl Iterator<Item = SystemTime> {
+        self.all_local().map(SystemTime::from)
+    }
+
+    /// Like [`Weekly::all`] but also walkable from the back, only for rules
+    /// whose end is bounded
+    pub fn all_bounded(&self) -> Option<BoundedIterator> {
+        self.dates().bounded()
+    }
+
+    /// Like [`Weekly::all`] but keeping each occurrence in the rule's timezone,
+    /// ready to be formatted as a wall-clock time
+    pub fn all_local(&self) -> impl Iterator<Item = DateTime<TimeZoneSpec>> {
+        self.dates()
+    }
+
+    /// Like [`Weekly::all_local`] but showing each occurrence in `display_tz`.
+    /// The occurrences are still computed in the rule's timezone, so they
+    /// follow its DST changes rather than those of `display_tz`.
+    pub fn all_in(&self, display_tz: Tz) -> impl Iterator<Item = DateTime<Tz>> {
+        self.dates()
+            .map(move |date| date.with_timezone(&display_tz))
+    }
+
+    /// Every occurrence moved by `by`, which can be negative, e.g. a reminder
+    /// 15 minutes before each one with `Duration::minutes(-15)`
+    pub fn shifted(&self, by: Duration) -> impl Iterator<Item = SystemTime> {
+        self.dates().map(move |date| (date + by).into())
+    }
+
+    pub(crate) fn is_all_day(&self) -> bool {
+        self.all_day
+    }
+
+    /// Every occurrence from dtstart on
+    pub(crate) fn dates(&self) -> TzDateIterator {
+        TzDateIterator::new(
+            self.timezone.from_utc_datetime(&self.dtstart),
+            chrono::Duration::weeks(self.interval as i64),
+            self.end,
+        )
+        .ambiguous(self.dst_ambiguous)
+        .nonexistent(self.dst_nonexistent)
+    }
+
+    /// Exports up to `max` occurrences as CSV rows of
+    /// `index,iso8601_local,unix_seconds`, rendering local times in `tz`
+    pub fn all_csv(&self, tz: Tz, max: usize) -> String {
+        crate::csv::to_csv(self.all(), tz, max)
+    }
+
     pub fn after(&self, min: SystemTime) -> impl Iterator<Item = SystemTime> {
-        let min = self.timezone.from_utc_datetime(&from_system_to_naive(min));
+        self.resume(min).1.map(SystemTime::from)
+    }
+
+    /// Each occurrence along with its index in the series
+    pub fn enumerate(&self) -> impl Iterator<Item = (usize, SystemTime)> {
+        self.all().enumerate()
+    }
+
+    /// Like [`Weekly::after`] but each occurrence comes with its index in the
+    /// whole series, counting from dtstart rather than from `min`
+    pub fn enumerate_after(&self, min: SystemTime) -> impl Iterator<Item = (usize, SystemTime)> {
+        let (skipped, dates) = self.resume(min);
+        dates
+            .enumerate()
+            .map(move |(i, date)| (skipped + i, date.into()))
+    }
+
+    /// The occurrences at or after `min`, along with how many were skipped
+    pub(crate) fn resume(&self, min: SystemTime) -> (usize, TzDateIterator) {
         let dtstart = self.timezone.from_utc_datetime(&self.dtstart);
-        let mut end = self.end;
 
-        let cursor = if min <= dtstart {
-            dtstart
-        } else {
+        TzDateIterator::new(
+            dtstart,
+            chrono::Duration::weeks(self.interval as i64),
+            self.end,
+        )
+        .ambiguous(self.dst_ambiguous)
+        .nonexistent(self.dst_nonexistent)
+        .resume(min, |min| {
             const DAYS_IN_WEEK: u32 = 7;
             let time = dtstart.time();
-            let start_date = dtstart.date();
+            let start_date = dtstart.date_naive();
 
             let date = {
-                let date = min.date();
+                let date = min.date_naive();
                 let mut difference = (start_date.weekday().number_from_monday() + DAYS_IN_WEEK
                     - date.weekday().number_from_monday())
                     % DAYS_IN_WEEK;
@@ -58,34 +347,117 @@ impl Weekly {
                     difference = 7;
                 }
 
-                date + Duration::days(difference as i64)
+                date.checked_add_signed(Duration::days(difference as i64))?
             };
 
-            if let End::Count(ref mut c) = end {
-                *c = c.saturating_sub((date - start_date).num_weeks() as usize);
+            // round up to the next week that is a whole number of intervals
+            // from dtstart's
+            let weeks = (date - start_date).num_weeks() as u64;
+            let mut skipped = weeks.div_ceil(self.interval);
+            let local = |skipped: u64| {
+                let weeks = i64::try_from(skipped.checked_mul(self.interval)?).ok()?;
+                let date = start_date.checked_add_signed(Duration::try_weeks(weeks)?)?;
+                Some(date.and_time(time))
+            };
+
+            // an occurrence moved by an offset change can land on either
+            // side of min whatever its wall-clock time
+            let lands = |skipped| match dst::resolve(
+                self.timezone,
+                local(skipped)?,
+                self.dst_ambiguous,
+                self.dst_nonexistent,
+            ) {
+                Resolution::At(date) => Some(date),
+                _ => None,
+            };
+            if matches!(lands(skipped), Some(date) if date < min) {
+                skipped += 1;
+            } else if skipped > 0 && matches!(lands(skipped - 1), Some(date) if date >= min) {
+                skipped -= 1;
             }
 
-            date.and_time(time).expect("bug: and_time")
-        };
+            Some((usize::try_from(skipped).ok()?, local(skipped)?))
+        })
+    }
 
-        TzDateIterator {
-            end: end.into(),
-            interval: chrono::Duration::days(self.interval as i64),
-            cursor,
-        }
+    /// Up to the first `n` occurrences, fewer if the rule ends before then
+    pub fn first_n(&self, n: usize) -> Vec<SystemTime> {
+        self.all().take(n).collect()
+    }
+
+    /// Up to `n` occurrences at or after `min`, fewer if the rule ends
+    /// before then
+    pub fn after_n(&self, min: SystemTime, n: usize) -> Vec<SystemTime> {
+        self.after(min).take(n).collect()
+    }
+
+    /// Collects at most `max` occurrences whatever the end, safe even for
+    /// rules that never end. The flag is set when occurrences were left out.
+    pub fn collect_capped(&self, max: usize) -> (Vec<SystemTime>, bool) {
+        let mut dates = self.all();
+        let capped = dates.by_ref().take(max).collect();
+
+        (capped, dates.next().is_some())
     }
-}
 
-fn from_system_to_naive(time: SystemTime) -> NaiveDateTime {
-    let duration = time.duration_since(SystemTime::UNIX_EPOCH).expect("bug");
-    NaiveDateTime::from_timestamp(duration.as_secs() as i64, duration.subsec_nanos())
+    /// The DST transitions that shift the occurrences in `[start, end)`
+    /// relative to each other, see [`Weekly::all_local`]
+    pub fn dst_transitions(&self, start: SystemTime, end: SystemTime) -> Vec<SystemTime> {
+        let (_, dates) = self.resume(start);
+        dst::transitions(dates.take_while(|date| SystemTime::from(*date) < end))
+    }
+
+    /// What the rule does in words, e.g. "Every 2 weeks until 2025-12-31."
+    pub fn describe(&self) -> String {
+        format!(
+            "{}{}.",
+            describe::every(self.interval, "week"),
+            describe::end(self.end, self.timezone)
+        )
+    }
+
+    /// The number of occurrences, `None` when the rule never ends. Bounded
+    /// rules are counted off the calendar without walking every occurrence,
+    /// see [`Weekly::all`] for the exact series it matches.
+    pub fn estimated_count(&self) -> Option<usize> {
+        self.dates().estimated_count()
+    }
+
+    /// The occurrences within the calendar year `year` in the rule's timezone,
+    /// none for a year past the range of dates chrono can represent
+    pub fn in_year(&self, year: i32) -> impl Iterator<Item = SystemTime> {
+        let (start, end) = dst::year_bounds(self.timezone, year)
+            .unwrap_or((SystemTime::UNIX_EPOCH, SystemTime::UNIX_EPOCH));
+        self.after(start).take_while(move |&date| date < end)
+    }
+
+    /// The first occurrence at or after `min`
+    pub fn first_after(&self, min: SystemTime) -> Option<SystemTime> {
+        self.after(min).next()
+    }
+
+    /// Whether `t` is exactly one of the occurrences, down to the
+    /// nanosecond and after any DST correction
+    pub fn is_occurrence(&self, t: SystemTime) -> bool {
+        self.first_after(t) == Some(t)
+    }
+
+    /// The first occurrence strictly after `t`, counted off the calendar like
+    /// [`Weekly::after`] rather than walked to
+    pub fn next_after(&self, t: SystemTime) -> Option<SystemTime> {
+        self.first_after(t.checked_add(std::time::Duration::from_nanos(1))?)
+    }
 }
 
-fn local_tz() -> Tz {
-    iana_time_zone::get_timezone()
-        .expect("bug: could not get tz")
-        .parse()
-        .expect("bug: local tz could not be parsed")
+/// Iterates over [`Weekly::all`]
+impl IntoIterator for &Weekly {
+    type Item = SystemTime;
+    type IntoIter = RecurrenceIter;
+
+    fn into_iter(self) -> RecurrenceIter {
+        self.dates().into()
+    }
 }
 
 #[cfg(test)]
@@ -94,12 +466,16 @@ mod tests {
 
     use super::*;
     use approx::*;
+    use chrono::Timelike as _;
     use std::time::SystemTime;
 
     #[test]
     fn starts_today() {
         let now = SystemTime::now();
-        let daily = super::Weekly::new(Options::default());
+        let daily = super::Weekly::new(Options {
+            timezone: Some(chrono_tz::UTC.into()),
+            ..Options::default()
+        });
         let mut dates = daily.all();
 
         assert_abs_diff_eq!(
@@ -121,10 +497,11 @@ mod tests {
 
         let daily = super::Weekly::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             ..Options::default()
         });
 
-        let first = daily.all().nth(0).unwrap();
+        let first = daily.all().next().unwrap();
 
         assert_eq!(dtstart, first);
     }
@@ -134,6 +511,7 @@ mod tests {
         let dtstart = july_first();
         let daily = super::Weekly::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             ..Options::default()
         });
         let mut dates = daily.all().skip(1);
@@ -145,6 +523,7 @@ mod tests {
     #[test]
     fn count_limit() {
         let dates = super::Weekly::new(Options {
+            timezone: Some(chrono_tz::UTC.into()),
             end: End::Count(2),
             ..Options::default()
         });
@@ -155,6 +534,7 @@ mod tests {
     #[test]
     fn until_limit() {
         let dates = super::Weekly::new(Options {
+            timezone: Some(chrono_tz::UTC.into()),
             end: End::Until(SystemTime::now() + 3 * ONE_WEEK + ONE_DAY),
             ..Options::default()
         });
@@ -164,11 +544,265 @@ mod tests {
         assert_eq!(4, count);
     }
 
+    #[test]
+    fn clone() {
+        let dates = super::Weekly::utc(july_first(), 2, End::Count(3));
+        let cloned = dates.clone();
+
+        assert_eq!(
+            dates.all().collect::<Vec<_>>(),
+            cloned.all().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn equality() {
+        let dates = |interval| {
+            super::Weekly::new(Options {
+                dtstart: Some(july_first()),
+                interval: Some(interval),
+                timezone: Some(chrono_tz::UTC.into()),
+                end: End::Until(july_first() + 10 * ONE_WEEK),
+                ..Options::default()
+            })
+        };
+
+        assert_eq!(dates(2), dates(2));
+        assert_ne!(dates(2), dates(3));
+    }
+
+    #[test]
+    fn first_n() {
+        let dtstart = july_first();
+        let dates = super::Weekly::utc(dtstart, 1, End::Count(2));
+
+        assert_eq!(vec![dtstart, dtstart + ONE_WEEK], dates.first_n(5));
+        assert_eq!(
+            vec![dtstart + ONE_WEEK],
+            dates.after_n(dtstart + ONE_DAY, 5)
+        );
+    }
+
+    #[test]
+    fn in_year() {
+        let thursday = chrono_tz::UTC
+            .with_ymd_and_hms(2019, 6, 6, 10, 0, 0)
+            .unwrap();
+        let dates = super::Weekly::utc(thursday.into(), 1, End::Never);
+
+        // 2020 is a leap year so it starts and ends on a Thursday
+        assert_eq!(53, dates.in_year(2020).count());
+        assert_eq!(52, dates.in_year(2021).count());
+        // only the second half of the year after dtstart
+        assert_eq!(30, dates.in_year(2019).count());
+        assert_eq!(0, dates.in_year(2018).count());
+    }
+
+    #[test]
+    fn utc() {
+        let dtstart = july_first();
+
+        let dates = super::Weekly::utc(dtstart, 2, End::Count(3));
+        let expected = super::Weekly::new(Options {
+            dtstart: Some(dtstart),
+            interval: Some(2),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(3),
+            ..Options::default()
+        });
+
+        assert_eq!(
+            expected.all().collect::<Vec<_>>(),
+            dates.all().collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "local-tz")]
+    #[test]
+    fn biweekly() {
+        let dtstart = july_first();
+        let dates = super::Weekly::biweekly(dtstart, End::Count(2));
+
+        assert_eq!(
+            vec![dtstart, dtstart + 2 * ONE_WEEK],
+            dates.all().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn dst_transitions() {
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 10, 20, 9, 0, 0)
+            .unwrap();
+        let dates = super::Weekly::builder()
+            .zoned_dtstart(dtstart)
+            .build()
+            .unwrap();
+
+        let fall_back =
+            SystemTime::from(chrono::Utc.with_ymd_and_hms(2019, 11, 3, 6, 0, 0).unwrap());
+        let start = dtstart.into();
+
+        assert_eq!(
+            vec![fall_back],
+            dates.dst_transitions(start, start + 4 * ONE_WEEK)
+        );
+        assert!(dates.dst_transitions(start, start + ONE_WEEK).is_empty());
+    }
+
+    #[test]
+    fn start_weekday() {
+        let monday = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2020, 6, 29, 9, 0, 0)
+            .unwrap();
+        let tuesday = monday + Duration::days(1);
+
+        let dates = super::Weekly::builder()
+            .zoned_dtstart(monday)
+            .start_weekday(Weekday::Tue)
+            .count(3)
+            .build()
+            .unwrap();
+
+        let expected: Vec<SystemTime> = (0..3)
+            .map(|week| (tuesday + Duration::weeks(week)).into())
+            .collect();
+        assert_eq!(expected, dates.all().collect::<Vec<_>>());
+
+        let dates = super::Weekly::builder()
+            .zoned_dtstart(monday)
+            .start_weekday(Weekday::Mon)
+            .build()
+            .unwrap();
+        assert_eq!(Some(monday.into()), dates.all().next());
+    }
+
+    #[test]
+    fn zoned_dtstart() {
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 11, 2, 23, 0, 0)
+            .unwrap();
+
+        let dates = super::Weekly::builder()
+            .zoned_dtstart(dtstart)
+            .build()
+            .unwrap();
+
+        assert_eq!(TimeZoneSpec::Named(chrono_tz::US::Eastern), dates.timezone);
+        assert_eq!(dtstart, dates.all_local().next().unwrap());
+    }
+
+    #[test]
+    fn zero_interval() {
+        let dates = super::Weekly::try_new(Options {
+            interval: Some(0),
+            ..Options::default()
+        });
+
+        assert_eq!(Some(RecurrenceError::ZeroInterval), dates.err());
+    }
+
+    #[test]
+    fn estimated_count() {
+        // 01:30 happens twice on the day clocks fall back
+        let dtstart = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 10, 6, 1, 30, 0)
+            .unwrap();
+        let fall_back = chrono_tz::US::Eastern
+            .with_ymd_and_hms(2019, 11, 3, 0, 0, 0)
+            .unwrap();
+
+        for quarter in 0..(4 * 4) {
+            let until = SystemTime::from(fall_back) + quarter * 15 * ONE_MINUTE;
+            let dates = super::Weekly::new(Options {
+                dtstart: Some(dtstart.into()),
+                timezone: Some(chrono_tz::US::Eastern.into()),
+                end: End::Until(until),
+                dst_ambiguous: AmbiguousPolicy::Second,
+                ..Options::default()
+            });
+
+            assert_eq!(Some(dates.all().count()), dates.estimated_count());
+        }
+
+        let counted = super::Weekly::utc(july_first(), 2, End::Count(7));
+        assert_eq!(Some(7), counted.estimated_count());
+    }
+
+    #[test]
+    fn after_lands_on_an_interval_multiple() {
+        let dtstart = july_first();
+        let dates = super::Weekly::utc(dtstart, 3, End::Never);
+
+        for weeks in 1..7 {
+            let min = dtstart + weeks * ONE_WEEK - ONE_DAY;
+            let expected = dtstart + weeks.div_ceil(3) * 3 * ONE_WEEK;
+
+            let mut after = dates.after(min);
+            assert_eq!(Some(expected), after.next(), "{}", weeks);
+            assert_eq!(Some(expected + 3 * ONE_WEEK), after.next(), "{}", weeks);
+        }
+    }
+
+    #[test]
+    fn next_after() {
+        let dtstart = july_first();
+        let dates = super::Weekly::utc(dtstart, 1, End::Count(2));
+
+        assert_eq!(Some(dtstart + ONE_WEEK), dates.next_after(dtstart));
+        assert_eq!(Some(dtstart), dates.next_after(dtstart - ONE_MINUTE));
+        assert_eq!(None, dates.next_after(dtstart + ONE_WEEK));
+    }
+
+    #[test]
+    fn describe() {
+        let until = chrono_tz::UTC
+            .with_ymd_and_hms(2026, 1, 1, 0, 0, 0)
+            .unwrap();
+
+        let every_other = super::Weekly::utc(july_first(), 2, End::Until(until.into()));
+        let weekly = super::Weekly::utc(july_first(), 1, End::Never);
+
+        assert_eq!("Every 2 weeks until 2025-12-31.", every_other.describe());
+        assert_eq!("Every week.", weekly.describe());
+    }
+
+    #[test]
+    fn unknown_timezone() {
+        let dates = super::Weekly::builder()
+            .timezone_name("Mars/Phobos")
+            .build();
+
+        let err = dates.unwrap_err();
+        assert_eq!(
+            RecurrenceError::UnknownTimezone("Mars/Phobos".to_owned()),
+            err
+        );
+        assert_eq!(
+            "unknown timezone: Mars/Phobos (expected an IANA name, e.g. Europe/Paris, or an offset, e.g. +05:30)",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn until_before_start() {
+        let dtstart = july_first();
+
+        let dates = super::Weekly::try_new(Options {
+            dtstart: Some(dtstart),
+            end: End::Until(dtstart - ONE_DAY),
+            ..Options::default()
+        });
+
+        assert_eq!(Some(RecurrenceError::UntilBeforeStart), dates.err());
+    }
+
     #[test]
     fn interval() {
         let dtstart = july_first();
         let dates = super::Weekly::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             interval: Some(4),
             ..Options::default()
         });
@@ -179,12 +813,15 @@ mod tests {
 
     #[test]
     fn dst_changes() {
-        let last_day_of_dst =
-            SystemTime::from(chrono_tz::US::Eastern.ymd(2019, 11, 2).and_hms(23, 0, 0));
+        let last_day_of_dst = SystemTime::from(
+            chrono_tz::US::Eastern
+                .with_ymd_and_hms(2019, 11, 2, 23, 0, 0)
+                .unwrap(),
+        );
 
         let dates = super::Weekly::new(Options {
             dtstart: Some(last_day_of_dst),
-            timezone: Some(chrono_tz::US::Eastern),
+            timezone: Some(chrono_tz::US::Eastern.into()),
             ..Options::default()
         });
 
@@ -192,16 +829,41 @@ mod tests {
         assert_eq!(last_day_of_dst + ONE_WEEK + ONE_HOUR, first_week_of_dst);
     }
 
+    #[test]
+    fn rev() {
+        let dtstart = SystemTime::from(
+            chrono_tz::US::Eastern
+                .with_ymd_and_hms(2019, 10, 19, 23, 0, 0)
+                .unwrap(),
+        );
+
+        let dates = super::Weekly::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::US::Eastern.into()),
+            end: End::Count(5),
+            ..Options::default()
+        });
+
+        let mut forward: Vec<_> = dates.all().collect();
+        forward.reverse();
+
+        assert_eq!(
+            forward,
+            dates.all_bounded().unwrap().rev().collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn after_before_dtstart() {
         let dtstart = july_first();
 
         let dates = super::Weekly::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             ..Options::default()
         });
 
-        let first = dates.after(dtstart - 40 * ONE_HOUR).nth(0).unwrap();
+        let first = dates.after(dtstart - 40 * ONE_HOUR).next().unwrap();
         assert_eq!(dtstart, first);
     }
 
@@ -211,10 +873,11 @@ mod tests {
 
         let dates = super::Weekly::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             ..Options::default()
         });
 
-        let first = dates.after(dtstart + ONE_MINUTE).nth(0).unwrap();
+        let first = dates.after(dtstart + ONE_MINUTE).next().unwrap();
         assert_eq!(dtstart + ONE_WEEK, first);
     }
 
@@ -224,23 +887,41 @@ mod tests {
 
         let dates = super::Weekly::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             ..Options::default()
         });
 
         let first = dates
             .after(dtstart + 2 * ONE_WEEK + ONE_DAY)
-            .nth(0)
+            .next()
             .unwrap();
 
         assert_eq!(dtstart + 3 * ONE_WEEK, first);
     }
 
+    #[test]
+    fn after_steps_by_interval_weeks() {
+        let dtstart = july_first();
+
+        let dates = super::Weekly::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            interval: Some(2),
+            ..Options::default()
+        });
+        let mut dates = dates.after(dtstart + ONE_MINUTE);
+        let first = dates.next().unwrap();
+
+        assert_eq!(first + 2 * ONE_WEEK, dates.next().unwrap());
+    }
+
     #[test]
     fn after_with_count() {
         let dtstart = july_first();
 
         let dates = super::Weekly::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             end: End::Count(4),
             ..Options::default()
         });
@@ -252,16 +933,87 @@ mod tests {
         assert_eq!(dates.after(dtstart + 12 * ONE_DAY).count(), 2);
     }
 
+    #[test]
+    fn after_with_count_and_interval() {
+        let dtstart = july_first();
+
+        let dates = super::Weekly::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            interval: Some(4),
+            end: End::Count(3),
+            ..Options::default()
+        });
+
+        // skipping 4 weeks only skips the occurrence on week 0
+        assert_eq!(2, dates.after(dtstart + 4 * ONE_WEEK).count());
+    }
+
     #[test]
     fn after_too_late() {
         let dtstart = july_first();
 
         let dates = super::Weekly::new(Options {
             dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
             end: End::Count(1),
             ..Options::default()
         });
 
         assert_eq!(dates.after(dtstart + 12 * ONE_DAY).count(), 0);
     }
+
+    #[test]
+    fn all_day() {
+        let afternoon = chrono_tz::America::New_York
+            .with_ymd_and_hms(2020, 10, 25, 15, 0, 0)
+            .unwrap();
+        let dates = super::Weekly::builder()
+            .zoned_dtstart(afternoon)
+            .all_day()
+            .build()
+            .unwrap();
+
+        // clocks fall back on 2020-11-01
+        let hours: Vec<_> = dates.all_local().take(3).map(|date| date.hour()).collect();
+        assert_eq!(vec![0, 0, 0], hours);
+        assert!(dates.to_options().all_day);
+    }
+
+    #[test]
+    fn after_across_fall_back() {
+        let dtstart = chrono_tz::America::New_York
+            .with_ymd_and_hms(2020, 10, 25, 1, 30, 0)
+            .unwrap();
+        let dates = super::Weekly::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::America::New_York.into()),
+            ..Options::default()
+        });
+
+        // 01:10 the second time around on 2020-11-01, after the first 01:30
+        let min = chrono::Utc.with_ymd_and_hms(2020, 11, 1, 6, 10, 0).unwrap();
+        let next = chrono_tz::America::New_York
+            .with_ymd_and_hms(2020, 11, 8, 1, 30, 0)
+            .unwrap();
+
+        assert_eq!(Some(next.into()), dates.after(min.into()).next());
+        assert_eq!(Some(next.into()), dates.first_after(min.into()));
+    }
+
+    #[test]
+    fn enumerate_after() {
+        let dtstart = july_first();
+
+        let dates = super::Weekly::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            interval: Some(2),
+            ..Options::default()
+        });
+        let mut dates = dates.enumerate_after(dtstart + 3 * 2 * ONE_WEEK);
+
+        assert_eq!((3, dtstart + 6 * ONE_WEEK), dates.next().unwrap());
+        assert_eq!((4, dtstart + 8 * ONE_WEEK), dates.next().unwrap());
+    }
 }
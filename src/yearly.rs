@@ -0,0 +1,650 @@
+use crate::{
+    dst::{self, Resolution},
+    tz_date_iterator::{
+        from_naive_to_system, from_system_to_naive, local_tz, matching_horizon, month_index,
+        nth_month, BoundedIterator, Step, TzDateIterator,
+    },
+    AmbiguousPolicy, End, NonexistentPolicy, RecurrenceError, RecurrenceIter, TimeZoneSpec,
+};
+use chrono::{DateTime, Datelike as _, NaiveDate, NaiveDateTime, TimeZone as _};
+use chrono_tz::Tz;
+use std::{convert::TryFrom, sync::Arc, time::SystemTime};
+
+/// Recurs on dtstart's month and day, or on the days of the year in
+/// `by_year_day`, or on every day of the ISO weeks in `by_week_no`, every
+/// `interval` years (ISO years with weeks). `by_month` swaps dtstart's month
+/// for a list of them. Years without that day (i.e. February 29th outside
+/// of leap years) are left out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Yearly {
+    interval: u64,
+    by_week_no: Vec<i8>,
+    by_month: Vec<u32>,
+    by_year_day: Vec<i16>,
+    timezone: TimeZoneSpec,
+    dtstart: NaiveDateTime,
+    end: End,
+    dst_ambiguous: AmbiguousPolicy,
+    dst_nonexistent: NonexistentPolicy,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Options {
+    pub interval: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::rfc3339::option"))]
+    pub dtstart: Option<SystemTime>,
+    pub timezone: Option<TimeZoneSpec>,
+    pub end: End,
+    pub dst_ambiguous: AmbiguousPolicy,
+    pub dst_nonexistent: NonexistentPolicy,
+    /// Lands on every day of these ISO 8601 weeks instead of dtstart's
+    /// day, at dtstart's time. Weeks start on Monday and week 1 is the one
+    /// with the year's first Thursday, so it can start in late December.
+    /// Negative weeks count back from the end of the year, e.g. -1 is the
+    /// last week. The interval then counts ISO years, which start on week
+    /// 1's Monday. A dtstart on another day moves to the first one that
+    /// matches.
+    pub by_week_no: Vec<i8>,
+    /// Lands on dtstart's day in each of these months instead of only
+    /// dtstart's, 1 for January to 12 for December. Along with
+    /// `by_week_no`, keeps only the days of those weeks in these months.
+    /// When no date ever matches, e.g. the 31st in February only,
+    /// `try_new` fails with `NoMatchingDate`.
+    pub by_month: Vec<u32>,
+    /// Lands on these days of the year instead of dtstart's day, at
+    /// dtstart's time: 1 is January 1st and 366 is December 31st of leap
+    /// years only. Negative days count back from the end of the year, e.g.
+    /// -1 is December 31st. Along with `by_week_no` or `by_month`, keeps
+    /// only the days that are in those too. When no date ever matches, e.g.
+    /// day 366 every 4 years from a year after a leap year, `try_new` fails
+    /// with `NoMatchingDate`.
+    pub by_year_day: Vec<i16>,
+}
+
+impl Yearly {
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Yearly::try_new`]
+    pub fn new(options: Options) -> Self {
+        Self::try_new(options).expect("invalid yearly options")
+    }
+
+    /// A recurrence computed in UTC, without looking up the local timezone
+    ///
+    /// # Panics
+    ///
+    /// When the options are invalid, see [`Yearly::try_new`]
+    pub fn utc(dtstart: SystemTime, interval: u64, end: End) -> Self {
+        Self::new(Options {
+            dtstart: Some(dtstart),
+            interval: Some(interval),
+            timezone: Some(chrono_tz::UTC.into()),
+            end,
+            ..Options::default()
+        })
+    }
+
+    pub fn try_new(options: Options) -> Result<Self, RecurrenceError> {
+        let dtstart = options.dtstart.unwrap_or_else(SystemTime::now);
+        let interval = options.interval.unwrap_or(1);
+
+        if interval == 0 {
+            return Err(RecurrenceError::ZeroInterval);
+        }
+        // years are stepped as months, counted as an i64
+        interval
+            .checked_mul(12)
+            .and_then(|months| i64::try_from(months).ok())
+            .ok_or(RecurrenceError::IntervalTooLarge)?;
+        if let Some(&week) = options
+            .by_week_no
+            .iter()
+            .find(|week| !(1..=53).contains(&week.abs()))
+        {
+            return Err(RecurrenceError::InvalidWeekNumber(week));
+        }
+        if let Some(&month) = options
+            .by_month
+            .iter()
+            .find(|month| !(1..=12).contains(*month))
+        {
+            return Err(RecurrenceError::InvalidMonth(month));
+        }
+        if let Some(&day) = options
+            .by_year_day
+            .iter()
+            .find(|day| !(1..=366).contains(&day.abs()))
+        {
+            return Err(RecurrenceError::InvalidYearDay(day));
+        }
+        let end = options.end.resolve(dtstart)?;
+
+        let timezone = options
+            .timezone
+            .or_else(local_tz)
+            .ok_or(RecurrenceError::MissingTimezone)?;
+        let mut dtstart = from_system_to_naive(dtstart)?;
+        if let Some(matches) = matching_days(
+            timezone.from_utc_datetime(&dtstart).date_naive(),
+            interval,
+            &options.by_year_day,
+            &options.by_week_no,
+            &options.by_month,
+        ) {
+            let within = matching_horizon(interval * 12);
+            dtstart = dst::first_day(timezone, dtstart, matches, within)?;
+        }
+
+        Ok(Yearly {
+            dtstart,
+            timezone,
+            interval,
+            by_week_no: options.by_week_no,
+            by_month: options.by_month,
+            by_year_day: options.by_year_day,
+            end,
+            dst_ambiguous: options.dst_ambiguous,
+            dst_nonexistent: options.dst_nonexistent,
+        })
+    }
+
+    /// The options that build this same rule back
+    pub fn to_options(&self) -> Options {
+        Options {
+            interval: Some(self.interval),
+            dtstart: Some(from_naive_to_system(self.dtstart)),
+            timezone: Some(self.timezone),
+            end: self.end,
+            dst_ambiguous: self.dst_ambiguous,
+            dst_nonexistent: self.dst_nonexistent,
+            by_week_no: self.by_week_no.clone(),
+            by_month: self.by_month.clone(),
+            by_year_day: self.by_year_day.clone(),
+        }
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = SystemTime> {
+        self.all_local().map(SystemTime::from)
+    }
+
+    /// Like [`Yearly::all`] but also walkable from the back, only for rules
+    /// whose end is bounded
+    pub fn all_bounded(&self) -> Option<BoundedIterator> {
+        self.dates().bounded()
+    }
+
+    /// Like [`Yearly::all`] but keeping each occurrence in the rule's
+    /// timezone, ready to be formatted as a wall-clock time
+    pub fn all_local(&self) -> impl Iterator<Item = DateTime<TimeZoneSpec>> {
+        self.dates()
+    }
+
+    /// Every occurrence from dtstart on
+    pub(crate) fn dates(&self) -> TzDateIterator {
+        let dtstart = self.timezone.from_utc_datetime(&self.dtstart);
+        let matches = matching_days(
+            dtstart.date_naive(),
+            self.interval,
+            &self.by_year_day,
+            &self.by_week_no,
+            &self.by_month,
+        );
+        let step = match matches {
+            Some(matches) => {
+                Step::Matching(Arc::new(matches), matching_horizon(self.interval * 12))
+            }
+            None => Step::Months(self.interval * 12),
+        };
+
+        TzDateIterator::new(dtstart, step, self.end)
+            .ambiguous(self.dst_ambiguous)
+            .nonexistent(self.dst_nonexistent)
+    }
+
+    /// Exports up to `max` occurrences as CSV rows of
+    /// `index,iso8601_local,unix_seconds`, rendering local times in `tz`
+    pub fn all_csv(&self, tz: Tz, max: usize) -> String {
+        crate::csv::to_csv(self.all(), tz, max)
+    }
+
+    pub fn after(&self, min: SystemTime) -> impl Iterator<Item = SystemTime> {
+        self.resume(min).1.map(SystemTime::from)
+    }
+
+    /// Each occurrence along with its index in the series
+    pub fn enumerate(&self) -> impl Iterator<Item = (usize, SystemTime)> {
+        self.all().enumerate()
+    }
+
+    /// Like [`Yearly::after`] but each occurrence comes with its index in
+    /// the whole series, counting from dtstart rather than from `min`
+    pub fn enumerate_after(&self, min: SystemTime) -> impl Iterator<Item = (usize, SystemTime)> {
+        let (skipped, dates) = self.resume(min);
+        dates
+            .enumerate()
+            .map(move |(i, date)| (skipped + i, date.into()))
+    }
+
+    /// The occurrences at or after `min`, along with how many were skipped
+    pub(crate) fn resume(&self, min: SystemTime) -> (usize, TzDateIterator) {
+        if !(self.by_year_day.is_empty() && self.by_week_no.is_empty() && self.by_month.is_empty())
+        {
+            return self.dates().skip_before(min);
+        }
+
+        let dtstart = self.timezone.from_utc_datetime(&self.dtstart);
+
+        TzDateIterator::new(dtstart, Step::Months(self.interval * 12), self.end)
+            .ambiguous(self.dst_ambiguous)
+            .nonexistent(self.dst_nonexistent)
+            .resume(min, |min| {
+                let start = dtstart.naive_local();
+                let interval = self.interval as i64 * 12;
+                let months = month_index(min.date_naive()) - month_index(dtstart.date_naive());
+
+                // every step before min's month is skipped, as long as it has the day
+                let mut steps = months / interval;
+                let mut skipped = (0..steps)
+                    .filter(|step| matches!(nth_month(start, step * interval), Ok(Some(_))))
+                    .count();
+
+                loop {
+                    if let Some(cursor) = nth_month(start, steps.checked_mul(interval)?).ok()? {
+                        let resolved = dst::resolve(
+                            self.timezone,
+                            cursor,
+                            self.dst_ambiguous,
+                            self.dst_nonexistent,
+                        );
+                        if !matches!(resolved, Resolution::At(c) if c < min) {
+                            break Some((skipped, cursor));
+                        }
+                        skipped += 1;
+                    }
+                    steps += 1;
+                }
+            })
+    }
+
+    /// The first occurrence at or after `min`
+    pub fn first_after(&self, min: SystemTime) -> Option<SystemTime> {
+        self.after(min).next()
+    }
+}
+
+/// Whether a date is one the rule lands on, for rules that pick days
+/// rather than just stepping by years: in a year `interval` years on from
+/// `start`'s, and in each of `year_days`, `weeks` and `months` that lists
+/// any. Without days or weeks, only `start`'s day of the month is left.
+/// With weeks, the years counted are ISO years, so that a week is never
+/// split between a year the rule lands on and one it skips.
+fn matching_days(
+    start: NaiveDate,
+    interval: u64,
+    year_days: &[i16],
+    weeks: &[i8],
+    months: &[u32],
+) -> Option<impl Fn(NaiveDate) -> bool> {
+    if year_days.is_empty() && weeks.is_empty() && months.is_empty() {
+        return None;
+    }
+
+    let year_of = match weeks {
+        [] => |date: NaiveDate| date.year(),
+        _ => |date: NaiveDate| date.iso_week().year(),
+    };
+    let year = i64::from(year_of(start));
+    let interval = interval as i64;
+    let year_days = year_days.to_vec();
+    let weeks = weeks.to_vec();
+    let months = months.to_vec();
+
+    Some(move |date: NaiveDate| {
+        let on_day = match (year_days.as_slice(), weeks.as_slice()) {
+            ([], []) => date.day() == start.day(),
+            (year_days, weeks) => {
+                (year_days.is_empty() || on_year_days(date, year_days))
+                    && (weeks.is_empty() || in_iso_weeks(date, weeks))
+            }
+        };
+
+        (i64::from(year_of(date)) - year) % interval == 0
+            && (months.is_empty() || months.contains(&date.month()))
+            && on_day
+    })
+}
+
+/// Whether a date is one of the `days` of its year, counting negative ones
+/// back from December 31st
+fn on_year_days(date: NaiveDate, days: &[i16]) -> bool {
+    let ordinal = date.ordinal() as i16;
+    let last = if date.leap_year() { 366 } else { 365 };
+
+    days.iter()
+        .any(|&wanted| wanted == ordinal || wanted == ordinal - last - 1)
+}
+
+/// Whether a date is in one of the ISO 8601 `weeks` of its calendar year. A
+/// week spanning New Year counts in both, as the last week of one and week
+/// 1 of the other.
+fn in_iso_weeks(date: NaiveDate, weeks: &[i8]) -> bool {
+    let week = date.iso_week();
+    let number = week.week() as i8;
+    let last = weeks_in_year(week.year()) as i8;
+
+    weeks
+        .iter()
+        .any(|&wanted| wanted == number || wanted == number - last - 1)
+}
+
+/// 52 or 53, going by the week of December 28th, which is always the last
+fn weeks_in_year(year: i32) -> u32 {
+    NaiveDate::from_ymd_opt(year, 12, 28).map_or(52, |date| date.iso_week().week())
+}
+
+/// Iterates over [`Yearly::all`]
+impl IntoIterator for &Yearly {
+    type Item = SystemTime;
+    type IntoIter = RecurrenceIter;
+
+    fn into_iter(self) -> RecurrenceIter {
+        self.dates().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use chrono::Timelike as _;
+
+    #[test]
+    fn dtstart() {
+        let dtstart = july_first();
+
+        let dates = super::Yearly::new(Options {
+            dtstart: Some(dtstart),
+            timezone: Some(chrono_tz::UTC.into()),
+            ..Options::default()
+        });
+
+        assert_eq!(dtstart, dates.all().next().unwrap());
+    }
+
+    #[test]
+    fn iso_weeks_every_other_iso_year() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2025, 1, 1, 9, 0, 0)
+            .unwrap();
+        let dates = super::Yearly::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            interval: Some(2),
+            end: End::Count(26),
+            by_week_no: vec![1],
+            ..Options::default()
+        });
+        let days: Vec<_> = dates
+            .all_local()
+            .map(|date| (date.year(), date.month(), date.day()))
+            .collect();
+
+        // week 1 of 2026 starts on 2025-12-29 and is skipped whole, while
+        // week 1 of 2031 starts on 2030-12-30 and is kept whole
+        let expected: Vec<_> = (1..=5)
+            .map(|day| (2025, 1, day))
+            .chain((4..=10).map(|day| (2027, 1, day)))
+            .chain((1..=7).map(|day| (2029, 1, day)))
+            .chain([(2030, 12, 30), (2030, 12, 31)])
+            .chain((1..=5).map(|day| (2031, 1, day)))
+            .collect();
+        assert_eq!(expected, days);
+    }
+
+    #[test]
+    fn iso_week_numbers() {
+        let new_year = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 1, 1, 9, 0, 0)
+            .unwrap();
+        let weeks = |by_week_no| {
+            super::Yearly::new(Options {
+                dtstart: Some(new_year.into()),
+                timezone: Some(chrono_tz::UTC.into()),
+                end: End::Count(7),
+                by_week_no,
+                ..Options::default()
+            })
+        };
+        let days = |dates: &super::Yearly| -> Vec<_> {
+            dates
+                .all_local()
+                .map(|date| (date.month(), date.day()))
+                .collect()
+        };
+
+        // 2021 starts on a Friday, so week 1 is the one after
+        let first = weeks(vec![1]);
+        assert_eq!(
+            (4..=10).map(|day| (1, day)).collect::<Vec<_>>(),
+            days(&first)
+        );
+        assert!(first
+            .all_local()
+            .all(|date| date.weekday().number_from_monday() == date.day() - 3));
+        assert_eq!(
+            Some(SystemTime::from(new_year) + 3 * ONE_DAY),
+            first.all().next()
+        );
+
+        // January 1st to 3rd are the end of 2020's last week, week 53
+        let last = days(&weeks(vec![-1]));
+        let expected = vec![
+            (1, 1),
+            (1, 2),
+            (1, 3),
+            (12, 27),
+            (12, 28),
+            (12, 29),
+            (12, 30),
+        ];
+        assert_eq!(expected, last);
+
+        let invalid = super::Yearly::try_new(Options {
+            by_week_no: vec![54],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::InvalidWeekNumber(54)), invalid.err());
+    }
+
+    #[test]
+    fn by_month() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 1, 15, 9, 0, 0)
+            .unwrap();
+        let dates = super::Yearly::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(4),
+            by_month: vec![3, 9],
+            ..Options::default()
+        });
+        let months = |dates: &mut dyn Iterator<Item = SystemTime>| -> Vec<_> {
+            dates
+                .map(|date| {
+                    let date = DateTime::<chrono::Utc>::from(date);
+                    (date.year(), date.month(), date.day())
+                })
+                .collect()
+        };
+
+        // dtstart moves to the first of those months
+        let expected = vec![(2021, 3, 15), (2021, 9, 15), (2022, 3, 15), (2022, 9, 15)];
+        assert_eq!(expected, months(&mut dates.all()));
+        assert_eq!(
+            expected[2..],
+            months(&mut dates.after((dtstart + chrono::Duration::days(365)).into()))[..]
+        );
+
+        let invalid = super::Yearly::try_new(Options {
+            by_month: vec![3, 13],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::InvalidMonth(13)), invalid.err());
+
+        // February never has a 31st
+        let never = super::Yearly::try_new(Options {
+            dtstart: Some(dtstart.with_day(31).unwrap().into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            by_month: vec![2],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::NoMatchingDate), never.err());
+    }
+
+    #[test]
+    fn by_year_day() {
+        // 2024 is a leap year, 2025 isn't
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2024, 1, 1, 9, 0, 0)
+            .unwrap();
+        let dates = super::Yearly::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(6),
+            by_year_day: vec![1, 100, 366],
+            ..Options::default()
+        });
+        let days: Vec<_> = dates
+            .all_local()
+            .map(|date| (date.year(), date.month(), date.day(), date.hour()))
+            .collect();
+
+        let expected = vec![
+            (2024, 1, 1, 9),
+            (2024, 4, 9, 9),
+            (2024, 12, 31, 9),
+            (2025, 1, 1, 9),
+            (2025, 4, 10, 9),
+            (2026, 1, 1, 9),
+        ];
+        assert_eq!(expected, days);
+
+        let last = super::Yearly::new(Options {
+            dtstart: Some(dtstart.into()),
+            timezone: Some(chrono_tz::UTC.into()),
+            end: End::Count(2),
+            by_year_day: vec![-1],
+            ..Options::default()
+        });
+        let days: Vec<_> = last.all_local().map(|date| date.ordinal()).collect();
+        assert_eq!(vec![366, 365], days);
+
+        let invalid = super::Yearly::try_new(Options {
+            by_year_day: vec![-367],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::InvalidYearDay(-367)), invalid.err());
+
+        // every fourth year from 2021 is never a leap year
+        let never = super::Yearly::try_new(Options {
+            dtstart: Some(
+                chrono_tz::UTC
+                    .with_ymd_and_hms(2021, 1, 1, 9, 0, 0)
+                    .unwrap()
+                    .into(),
+            ),
+            interval: Some(4),
+            timezone: Some(chrono_tz::UTC.into()),
+            by_year_day: vec![366],
+            ..Options::default()
+        });
+        assert_eq!(Some(RecurrenceError::NoMatchingDate), never.err());
+    }
+
+    #[test]
+    fn multiple_years() {
+        let dtstart = july_first();
+        let dates = super::Yearly::utc(dtstart, 2, End::Never);
+        let mut dates = dates.all().skip(1);
+
+        // 2021 and 2022 are not leap years
+        assert_eq!(dtstart + 730 * ONE_DAY, dates.next().unwrap());
+        // but 2024 is
+        assert_eq!(dtstart + 1461 * ONE_DAY, dates.next().unwrap());
+    }
+
+    #[test]
+    fn leap_day() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2020, 2, 29, 9, 0, 0)
+            .unwrap();
+        let dates = super::Yearly::utc(dtstart.into(), 1, End::Count(3));
+
+        let years: Vec<_> = dates.all_local().map(|d| d.year()).collect();
+        assert_eq!(vec![2020, 2024, 2028], years);
+
+        let back: Vec<_> = dates
+            .all_bounded()
+            .unwrap()
+            .rev()
+            .map(|d| DateTime::<chrono::Utc>::from(d).year())
+            .collect();
+        assert_eq!(vec![2028, 2024, 2020], back);
+    }
+
+    #[test]
+    fn ends_at_the_last_representable_year() {
+        let last = NaiveDateTime::MAX.date().year();
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(last - 2, 3, 1, 9, 0, 0)
+            .unwrap()
+            .into();
+        let dates = super::Yearly::utc(dtstart, 1, End::Never);
+
+        assert_eq!(3, dates.all().count());
+    }
+
+    #[test]
+    fn interval_too_large() {
+        let dates = super::Yearly::try_new(Options {
+            interval: Some(u64::MAX / 2),
+            ..Options::default()
+        });
+
+        assert_eq!(Some(RecurrenceError::IntervalTooLarge), dates.err());
+    }
+
+    #[test]
+    fn zero_interval() {
+        let dates = super::Yearly::try_new(Options {
+            interval: Some(0),
+            ..Options::default()
+        });
+
+        assert_eq!(Some(RecurrenceError::ZeroInterval), dates.err());
+    }
+
+    #[test]
+    fn after() {
+        let dtstart = chrono_tz::UTC
+            .with_ymd_and_hms(2020, 2, 29, 9, 0, 0)
+            .unwrap();
+        let dates = super::Yearly::utc(dtstart.into(), 1, End::Count(3));
+
+        let min = chrono_tz::UTC
+            .with_ymd_and_hms(2021, 1, 1, 0, 0, 0)
+            .unwrap();
+        let leap = chrono_tz::UTC
+            .with_ymd_and_hms(2024, 2, 29, 9, 0, 0)
+            .unwrap();
+
+        let mut after = dates.enumerate_after(min.into());
+        assert_eq!(Some((1, leap.into())), after.next());
+        assert_eq!(1, after.count());
+    }
+}